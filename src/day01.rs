@@ -3,34 +3,52 @@
 use crate::utils;
 use anyhow::{anyhow, Result};
 
-fn part1(data: &[i64]) -> Result<i64> {
-    for c in utils::combinations(2, data.len()) {
-        let test = vec![data[c[0]], data[c[1]]];
-        if test.iter().sum::<i64>() == 2020 {
-            return Ok(test.iter().product());
+const TARGET: i64 = 2020;
+
+// Both parts stream the expense report. Entries are non-negative, so only
+// those no larger than TARGET can be part of a sum to TARGET; that bounds
+// what needs remembering to a count per value, however long the report.
+
+fn part1(data: impl Iterator<Item = i64>) -> Result<i64> {
+    let mut seen = vec![false; TARGET as usize + 1];
+    for x in data.filter(|x| (0..=TARGET).contains(x)) {
+        if seen[(TARGET - x) as usize] {
+            return Ok(x * (TARGET - x));
         }
+        seen[x as usize] = true;
     }
     Err(anyhow!("no result found"))
 }
 
-fn part2(data: &[i64]) -> Result<i64> {
-    for c in utils::combinations(3, data.len()) {
-        let test = vec![data[c[0]], data[c[1]], data[c[2]]];
-        if test.iter().sum::<i64>() == 2020 {
-            return Ok(test.iter().product());
+fn part2(data: impl Iterator<Item = i64>) -> Result<i64> {
+    // count each value, capped at 3 since that's as many as a sum can use
+    let mut counts = vec![0_u8; TARGET as usize + 1];
+    for x in data.filter(|x| (0..=TARGET).contains(x)) {
+        counts[x as usize] = (counts[x as usize] + 1).min(3);
+    }
+
+    // try every a <= b <= c with a + b + c == TARGET
+    for a in 0..=TARGET {
+        for b in a..=(TARGET - a) / 2 {
+            let test = [a, b, TARGET - a - b];
+            if test
+                .iter()
+                .all(|x| test.iter().filter(|y| *y == x).count() <= counts[*x as usize].into())
+            {
+                return Ok(test.iter().product());
+            }
         }
     }
     Err(anyhow!("no result found"))
 }
 
 pub fn run() -> Result<(i64, i64)> {
-    let data = utils::read_i64s("data/input-01.txt")?;
     let answers = utils::read_i64s("data/output-01.txt")?;
 
-    let p1 = part1(&data)?;
+    let p1 = utils::try_stream(utils::stream_parsed("data/input-01.txt")?, part1)?;
     assert_eq!(p1, answers[0]);
 
-    let p2 = part2(&data)?;
+    let p2 = utils::try_stream(utils::stream_parsed("data/input-01.txt")?, part2)?;
     assert_eq!(p2, answers[1]);
 
     Ok((p1, p2))
@@ -1,48 +1,162 @@
 // ==== day 2
 
-use crate::utils;
-use anyhow::Result;
-use regex::Regex;
+use crate::utils::{self, ParseError};
+use anyhow::{anyhow, Context, Result};
+use policy::{PasswordPolicy, Sled, Toboggan, Units};
+use unicode_segmentation::UnicodeSegmentation;
+
+mod lint;
+mod policy;
 
 struct PasswordSpec {
     min: usize,
     max: usize,
-    required: char,
+    required: String, // one grapheme cluster, e.g. "n", "é" or "👍🏽"
     password: String,
 }
 
-fn valid_password_1(p: &PasswordSpec) -> bool {
-    let count = p.password.chars().filter(|c| c == &p.required).count();
-    p.min <= count && count <= p.max
+peg::parser! {
+    grammar spec_parser() for str {
+        rule number() -> usize
+            = n:quiet!{ $(['0'..='9']+) } {? n.parse().or(Err("a number")) }
+            / expected!("a number")
+
+        rule password() -> &'input str
+            = $((!([' ' | '\t']) [_])*)
+
+        // anything up to the ':' which ends it, so a ':' can be required too
+        rule required() -> &'input str
+            = quiet!{ $((!(":" [' ' | '\t']) !([' ' | '\t']) [_])+) } / expected!("a character")
+
+        // "4-8 n: dnjjrtclnzdnghnbnn"
+        pub rule spec() -> PasswordSpec
+            = min:number() "-" max:number() " "+ required:required() ":" " "+ password:password() {
+                PasswordSpec { min, max, required: required.to_string(), password: password.to_string() }
+            }
+    }
 }
 
-fn valid_password_2(p: &PasswordSpec) -> bool {
-    let char_a = p.password.chars().nth(p.min - 1).unwrap();
-    let char_b = p.password.chars().nth(p.max - 1).unwrap();
-    (char_a == p.required) ^ (char_b == p.required)
+// the (1-based, in chars) column of the required char in "min-max c: password"
+fn required_column(l: &str) -> usize {
+    let space = l.find([' ', '\t']).unwrap();
+    let start = space + l[space..].find(|c| c != ' ' && c != '\t').unwrap();
+    l[..start].chars().count() + 1
+}
+
+// parse one line of the database; errors are on line 1, for the caller to
+// offset
+fn parse_spec(l: &str) -> Result<PasswordSpec, ParseError> {
+    let spec = spec_parser::spec(l).map_err(|e| {
+        ParseError::new(1, format!("expected {}", e.expected)).with_column(e.location.column)
+    })?;
+    if spec.min > spec.max {
+        return Err(ParseError::new(
+            1,
+            format!(
+                "{}-{} is backwards: min is greater than max",
+                spec.min, spec.max
+            ),
+        )
+        .with_column(1));
+    }
+    if spec.required.graphemes(true).count() != 1 {
+        return Err(ParseError::new(
+            1,
+            format!("expected one character, found '{}'", spec.required),
+        )
+        .with_column(required_column(l)));
+    }
+    Ok(spec)
+}
+
+// count the passwords valid under each policy, in one pass over the database
+fn count_valid(
+    pwspecs: impl Iterator<Item = PasswordSpec>,
+    policies: &[&dyn PasswordPolicy],
+) -> Vec<usize> {
+    pwspecs.fold(vec![0; policies.len()], |mut counts, p| {
+        for (n, policy) in counts.iter_mut().zip(policies) {
+            *n += policy.check(&p) as usize;
+        }
+        counts
+    })
+}
+
+// stream the lines of a database as PasswordSpec{}, stopping at the first
+// which doesn't parse
+fn stream_database(filename: &str) -> Result<impl Iterator<Item = Result<PasswordSpec>>> {
+    let name = filename.to_string();
+    Ok(utils::stream_lines(filename)?.map(move |l| {
+        let (i, l) = l?;
+        parse_spec(&l)
+            .map_err(|e| e.offset(i - 1))
+            .with_context(|| format!("failed to parse {} (try day02 lint)", name))
+    }))
 }
 
 pub fn run() -> Result<(usize, usize)> {
-    let data = utils::read_file("data/input-02.txt")?;
     let answers = utils::read_i64s("data/output-02.txt")?;
+    let pwspecs = stream_database("data/input-02.txt")?;
 
-    // parse lines like "4-8 n: dnjjrtclnzdnghnbnn" into vector of PasswordSpec{}
-    let re = Regex::new(r"(\d+)-(\d+) +([a-z]): +(\S*)")?;
-    let pwspecs: Vec<PasswordSpec> = re
-        .captures_iter(&data)
-        .map(|cap| PasswordSpec {
-            min: cap[1].parse().expect("parse error"),
-            max: cap[2].parse().expect("parse error"),
-            required: cap[3].chars().next().expect("string index error"),
-            password: (&cap[4]).to_string(),
-        })
-        .collect();
-
-    let p1 = pwspecs.iter().filter(|p| valid_password_1(&p)).count();
+    let counts = utils::try_stream(pwspecs, |p| {
+        Ok(count_valid(
+            p,
+            &[&Sled(Units::Chars), &Toboggan(Units::Chars)],
+        ))
+    })?;
+    let (p1, p2) = (counts[0], counts[1]);
     assert_eq!(p1, answers[0] as usize);
-
-    let p2 = pwspecs.iter().filter(|p| valid_password_2(&p)).count();
     assert_eq!(p2, answers[1] as usize);
 
     Ok((p1, p2))
 }
+
+// command line tools for password databases
+pub fn cli(args: &[String]) -> Result<()> {
+    match args {
+        [cmd, filename, policies @ ..] if cmd == "audit" => {
+            // count the passwords valid under each policy, by default the
+            // puzzle's two
+            let policies: Vec<Box<dyn PasswordPolicy>> = if policies.is_empty() {
+                vec![Box::new(Sled(Units::Chars)), Box::new(Toboggan(Units::Chars))]
+            } else {
+                policies
+                    .iter()
+                    .map(|p| policy::parse_policy(p))
+                    .collect::<Result<_>>()?
+            };
+            let policies: Vec<&dyn PasswordPolicy> = policies.iter().map(|p| p.as_ref()).collect();
+            let mut total = 0;
+            let pwspecs = stream_database(filename)?.inspect(|_| total += 1);
+            let counts = utils::try_stream(pwspecs, |p| Ok(count_valid(p, &policies)))?;
+            for (policy, n) in policies.iter().zip(counts) {
+                println!("{:>6} of {} valid under {}", n, total, policy);
+            }
+            Ok(())
+        }
+        [cmd, filename, units @ ..] if cmd == "lint" && units.len() <= 1 => {
+            // every problem in the database, rather than just the first, with
+            // positions in chars or the given units
+            let units = match units {
+                [units] => units.parse()?,
+                _ => Units::default(),
+            };
+            let (lines, problems) = lint::lint(filename, units)?;
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            match problems.len() {
+                0 => {
+                    println!("{} lines, no problems", lines);
+                    Ok(())
+                }
+                n => Err(anyhow!("{} problems in {} lines of {}", n, lines, filename)),
+            }
+        }
+        _ => Err(anyhow!(
+            "usage: day02 audit FILE [POLICY...] | day02 lint FILE [bytes|chars|graphemes]\n       \
+             where POLICY is sled, toboggan, min-length:N, classes:C+C..., forbid:S+S...\n       \
+             or and(POLICY, ...), or(POLICY, ...), xor(POLICY, ...); C is lower, upper, digit or symbol"
+        )),
+    }
+}
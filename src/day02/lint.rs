@@ -0,0 +1,61 @@
+// ==== day 2: database linting
+//
+// Everything wrong with a password database, each with its line number,
+// instead of stopping at the first line which doesn't parse. Besides lines
+// which don't parse (including min > max), a position outside the password
+// is reported: the toboggan policy treats it as not matching, which is
+// probably not what the database meant; so is a required char which is more
+// than one unit, as it can never be at a position.
+
+use super::{parse_spec, policy::Units, required_column};
+use crate::utils::{self, ParseError};
+use anyhow::Result;
+
+// the number of lines, and the problems found on them
+pub fn lint(filename: &str, units: Units) -> Result<(usize, Vec<ParseError>)> {
+    let mut lines = 0;
+    let mut problems = Vec::new();
+    for l in utils::stream_lines(filename)? {
+        let (i, l) = l?;
+        lines = i;
+        let spec = match parse_spec(&l) {
+            Ok(spec) => spec,
+            Err(e) => {
+                problems.push(e.offset(i - 1));
+                continue;
+            }
+        };
+        if units.len(&spec.required) != 1 {
+            problems.push(
+                ParseError::new(
+                    i,
+                    format!(
+                        "'{}' is {} {}, so it can't be at a position",
+                        spec.required,
+                        units.len(&spec.required),
+                        units
+                    ),
+                )
+                .with_column(required_column(&l)),
+            );
+        }
+        let len = units.len(&spec.password);
+        // the columns of min and max in "min-max c: password"
+        let max_column = l.find('-').unwrap() + 2;
+        for (pos, column) in [(spec.min, 1), (spec.max, max_column)] {
+            if pos == 0 || pos > len {
+                problems.push(
+                    ParseError::new(
+                        i,
+                        format!(
+                            "position {} is outside the password '{}' (length {} in {})",
+                            pos, spec.password, len, units
+                        ),
+                    )
+                    .with_column(column),
+                );
+            }
+        }
+    }
+    Ok((lines, problems))
+}
@@ -0,0 +1,319 @@
+// ==== day 2: password policies
+//
+// A policy decides whether a password in the database is acceptable. The two
+// from the puzzle use the spec on each line; the others only look at the
+// password itself, and can be combined with and/or/xor. Policies are written
+// as expressions so they can be chosen on the command line, e.g.
+//
+//   and(toboggan, min-length:8, classes:upper+digit, forbid:password+1234)
+//
+// Positions, counts and lengths are in chars unless the policy says otherwise
+// with a suffix, e.g. "toboggan@graphemes" or "min-length:8@bytes".
+
+use super::PasswordSpec;
+use anyhow::{anyhow, Result};
+use std::{fmt, str::FromStr};
+use unicode_segmentation::UnicodeSegmentation;
+
+// what a position in a password counts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Units {
+    Bytes,
+    #[default]
+    Chars, // Unicode scalar values
+    Graphemes, // extended grapheme clusters, i.e. what a reader sees as one character
+}
+
+const UNITS: [(&str, Units); 3] = [
+    ("bytes", Units::Bytes),
+    ("chars", Units::Chars),
+    ("graphemes", Units::Graphemes),
+];
+
+impl Units {
+    // the text split into units, each as its bytes (a byte of a multi-byte
+    // char isn't a str)
+    pub fn split(self, s: &str) -> Box<dyn Iterator<Item = &[u8]> + '_> {
+        match self {
+            Units::Bytes => Box::new(s.as_bytes().chunks(1)),
+            Units::Chars => Box::new(
+                s.char_indices()
+                    .map(move |(i, c)| &s.as_bytes()[i..i + c.len_utf8()]),
+            ),
+            Units::Graphemes => Box::new(s.graphemes(true).map(str::as_bytes)),
+        }
+    }
+
+    pub fn len(self, s: &str) -> usize {
+        match self {
+            Units::Bytes => s.len(),
+            _ => self.split(s).count(),
+        }
+    }
+}
+
+impl FromStr for Units {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UNITS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, units)| *units)
+            .ok_or_else(|| anyhow!("unknown units '{}': use bytes, chars or graphemes", s))
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = UNITS.iter().find(|(_, u)| u == self).unwrap();
+        write!(f, "{}", name)
+    }
+}
+
+// a policy's name, with its units if they aren't the default
+fn write_with_units(f: &mut fmt::Formatter<'_>, name: &str, units: Units) -> fmt::Result {
+    match units {
+        Units::Chars => write!(f, "{}", name),
+        _ => write!(f, "{}@{}", name, units),
+    }
+}
+
+pub trait PasswordPolicy: fmt::Display {
+    fn check(&self, p: &PasswordSpec) -> bool;
+}
+
+// the sled rental place's policy: the required char occurs min..=max times
+pub struct Sled(pub Units);
+
+impl PasswordPolicy for Sled {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        let required = p.required.as_bytes();
+        let count = self.0.split(&p.password).filter(|u| *u == required).count();
+        p.min <= count && count <= p.max
+    }
+}
+
+impl fmt::Display for Sled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_with_units(f, "sled", self.0)
+    }
+}
+
+// the Toboggan Corporate policy: the required char is at exactly one of the
+// (1-based) positions min and max; a position outside the password doesn't
+// match (and is reported by day02 lint)
+pub struct Toboggan(pub Units);
+
+impl PasswordPolicy for Toboggan {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        let at = |pos: usize| {
+            pos.checked_sub(1)
+                .and_then(|i| self.0.split(&p.password).nth(i))
+                == Some(p.required.as_bytes())
+        };
+        at(p.min) ^ at(p.max)
+    }
+}
+
+impl fmt::Display for Toboggan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_with_units(f, "toboggan", self.0)
+    }
+}
+
+// at least this many units
+pub struct MinLength(pub usize, pub Units);
+
+impl PasswordPolicy for MinLength {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        self.1.len(&p.password) >= self.0
+    }
+}
+
+impl fmt::Display for MinLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_with_units(f, &format!("min-length:{}", self.0), self.1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    Symbol, // anything else which isn't whitespace
+}
+
+const CHAR_CLASSES: [(&str, CharClass); 4] = [
+    ("lower", CharClass::Lower),
+    ("upper", CharClass::Upper),
+    ("digit", CharClass::Digit),
+    ("symbol", CharClass::Symbol),
+];
+
+impl CharClass {
+    fn contains(self, c: char) -> bool {
+        match self {
+            CharClass::Lower => c.is_lowercase(),
+            CharClass::Upper => c.is_uppercase(),
+            CharClass::Digit => c.is_numeric(),
+            CharClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CHAR_CLASSES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, class)| *class)
+            .ok_or_else(|| anyhow!("unknown character class '{}'", s))
+    }
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = CHAR_CLASSES.iter().find(|(_, c)| c == self).unwrap();
+        write!(f, "{}", name)
+    }
+}
+
+// at least one char of each class
+pub struct Classes(pub Vec<CharClass>);
+
+impl PasswordPolicy for Classes {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        self.0
+            .iter()
+            .all(|class| p.password.chars().any(|c| class.contains(c)))
+    }
+}
+
+impl fmt::Display for Classes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let classes: Vec<String> = self.0.iter().map(CharClass::to_string).collect();
+        write!(f, "classes:{}", classes.join("+"))
+    }
+}
+
+// none of these substrings
+pub struct Forbid(pub Vec<String>);
+
+impl PasswordPolicy for Forbid {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        !self.0.iter().any(|s| p.password.contains(s.as_str()))
+    }
+}
+
+impl fmt::Display for Forbid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "forbid:{}", self.0.join("+"))
+    }
+}
+
+// every policy
+pub struct And(pub Vec<Box<dyn PasswordPolicy>>);
+
+// at least one policy
+pub struct Or(pub Vec<Box<dyn PasswordPolicy>>);
+
+// an odd number of policies, i.e. exactly one of two
+pub struct Xor(pub Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for And {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        self.0.iter().all(|policy| policy.check(p))
+    }
+}
+
+impl PasswordPolicy for Or {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        self.0.iter().any(|policy| policy.check(p))
+    }
+}
+
+impl PasswordPolicy for Xor {
+    fn check(&self, p: &PasswordSpec) -> bool {
+        self.0
+            .iter()
+            .fold(false, |acc, policy| acc ^ policy.check(p))
+    }
+}
+
+fn write_combinator(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    policies: &[Box<dyn PasswordPolicy>],
+) -> fmt::Result {
+    let policies: Vec<String> = policies.iter().map(|p| p.to_string()).collect();
+    write!(f, "{}({})", name, policies.join(", "))
+}
+
+impl fmt::Display for And {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_combinator(f, "and", &self.0)
+    }
+}
+
+impl fmt::Display for Or {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_combinator(f, "or", &self.0)
+    }
+}
+
+impl fmt::Display for Xor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_combinator(f, "xor", &self.0)
+    }
+}
+
+peg::parser! {
+    grammar policy_parser() for str {
+        rule _() = [' ']*
+
+        rule number() -> usize
+            = n:$(['0'..='9']+) {? n.parse().or(Err("a number")) }
+
+        // a list item: anything up to a separator
+        rule item() -> &'input str
+            = quiet!{ $((!['+' | ',' | '(' | ')' | ' '] [_])+) } / expected!("a name")
+
+        rule class() -> CharClass
+            = c:item() {? c.parse().or(Err("lower, upper, digit or symbol")) }
+
+        rule units() -> Units
+            = "@" u:item() {? u.parse().or(Err("bytes, chars or graphemes")) }
+            / "" { Units::default() }
+
+        rule policies() -> Vec<Box<dyn PasswordPolicy>>
+            = "(" _ ps:(policy() ++ (_ "," _)) _ ")" { ps }
+
+        pub rule policy() -> Box<dyn PasswordPolicy>
+            = "sled" u:units() { Box::new(Sled(u)) }
+            / "toboggan" u:units() { Box::new(Toboggan(u)) }
+            / "min-length:" n:number() u:units() { Box::new(MinLength(n, u)) }
+            / "classes:" cs:(class() ++ "+") { Box::new(Classes(cs)) }
+            / "forbid:" ss:(item() ++ "+") {
+                Box::new(Forbid(ss.iter().map(|s| s.to_string()).collect()))
+            }
+            / "and" _ ps:policies() { Box::new(And(ps)) }
+            / "or" _ ps:policies() { Box::new(Or(ps)) }
+            / "xor" _ ps:policies() { Box::new(Xor(ps)) }
+    }
+}
+
+// a policy from its expression, e.g. "or(sled, min-length:12)"
+pub fn parse_policy(s: &str) -> Result<Box<dyn PasswordPolicy>> {
+    policy_parser::policy(s.trim()).map_err(|e| {
+        anyhow!(
+            "bad policy '{}': expected {} at column {}",
+            s,
+            e.expected,
+            e.location.column
+        )
+    })
+}
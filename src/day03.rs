@@ -1,35 +1,142 @@
 // ==== day 3
 
-use crate::utils;
-use anyhow::Result;
+use crate::utils::{self, Grid, Wrap};
+use anyhow::{anyhow, Context, Result};
+use std::{convert::TryFrom, fmt, ops::RangeInclusive, str::FromStr};
 
-fn count_trees(data: &str, right: usize, down: usize) -> Result<usize> {
-    Ok(data
-        .lines()
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Square {
+    Open,
+    Tree,
+}
+
+impl TryFrom<char> for Square {
+    type Error = anyhow::Error;
+
+    fn try_from(c: char) -> Result<Self> {
+        match c {
+            '.' => Ok(Square::Open),
+            '#' => Ok(Square::Tree),
+            _ => Err(anyhow!("unknown map square '{}'", c)),
+        }
+    }
+}
+
+// (right, down): how far the toboggan moves each step; right may be negative
+type Slope = (i64, usize);
+
+// the slopes checked for part 2
+const SLOPES: [Slope; 5] = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+
+// the map repeats to the left and right, so its columns wrap around; one
+// lookup per row visited
+fn count_trees(map: &Grid<Square>, (right, down): Slope) -> Result<usize> {
+    if down == 0 {
+        return Err(anyhow!("slope ({}, 0) never reaches the bottom", right));
+    }
+    Ok((0..map.height())
         .step_by(down)
         .enumerate()
-        .filter(|(i, s)| {
-            s.chars()
-                .nth((i * right) % s.len())
-                .expect("string index error")
-                == '#'
-        })
+        .filter(|(i, row)| map.get_signed(*row as i64, *i as i64 * right) == Some(&Square::Tree))
         .count())
 }
 
+// the tree count for every slope with right and down in the given ranges, in
+// order of down then right
+fn survey(
+    map: &Grid<Square>,
+    rights: RangeInclusive<i64>,
+    downs: RangeInclusive<usize>,
+) -> Result<Vec<(Slope, usize)>> {
+    downs
+        .flat_map(|down| rights.clone().map(move |right| (right, down)))
+        .map(|slope| Ok((slope, count_trees(map, slope)?)))
+        .collect()
+}
+
+// the slope hitting the fewest trees; the first surveyed, on a tie
+fn fewest_trees(survey: &[(Slope, usize)]) -> Option<(Slope, usize)> {
+    survey.iter().copied().min_by_key(|(_, trees)| *trees)
+}
+
+// "A..B" (inclusive, with A <= B), or just "A"
+fn parse_range<T>(s: &str) -> Result<RangeInclusive<T>>
+where
+    T: FromStr + Copy + PartialOrd + fmt::Display,
+    T::Err: fmt::Display,
+{
+    let parse = |n: &str| n.parse().map_err(|e| anyhow!("bad range '{}': {}", s, e));
+    match s.split_once("..") {
+        Some((from, to)) => {
+            let (from, to) = (parse(from)?, parse(to)?);
+            if from > to {
+                return Err(anyhow!(
+                    "bad range '{}': {} is greater than {}; write the smaller end first",
+                    s,
+                    from,
+                    to
+                ));
+            }
+            Ok(from..=to)
+        }
+        None => {
+            let n = parse(s)?;
+            Ok(n..=n)
+        }
+    }
+}
+
 pub fn run() -> Result<(usize, usize)> {
     let data = utils::read_file("data/input-03.txt")?;
     let answers = utils::read_i64s("data/output-03.txt")?;
 
-    let p1 = count_trees(&data, 3, 1)?;
+    let map = Grid::parse(&data)?.with_wrap(Wrap::Horizontal);
+
+    let p1 = count_trees(&map, (3, 1))?;
     assert_eq!(p1, answers[0] as usize);
 
-    let p2 = count_trees(&data, 1, 1)?
-        * p1
-        * count_trees(&data, 5, 1)?
-        * count_trees(&data, 7, 1)?
-        * count_trees(&data, 1, 2)?;
+    let p2 = SLOPES
+        .iter()
+        .map(|slope| count_trees(&map, *slope))
+        .product::<Result<usize>>()?;
     assert_eq!(p2, answers[1] as usize);
 
     Ok((p1, p2))
 }
+
+const USAGE: &str = "usage: day03 slopes FILE [RIGHT[..RIGHT] [DOWN[..DOWN]]]";
+
+// command line tools for toboggan maps
+pub fn cli(args: &[String]) -> Result<()> {
+    match args {
+        [cmd, filename, ranges @ ..] if cmd == "slopes" && ranges.len() <= 2 => {
+            // the trees hit on every slope in the ranges (by default, right
+            // -7..7 and down 1..2), and the slope which hits fewest
+            let rights = ranges
+                .first()
+                .map_or(Ok(-7..=7), |r| parse_range(r))
+                .with_context(|| USAGE)?;
+            let downs = ranges
+                .get(1)
+                .map_or(Ok(1..=2), |r| parse_range(r))
+                .with_context(|| USAGE)?;
+            if *downs.start() == 0 {
+                return Err(anyhow!(
+                    "bad range '{}': DOWN must be at least 1, or the toboggan never reaches the bottom",
+                    ranges[1]
+                )
+                .context(USAGE));
+            }
+            let map = Grid::parse(&utils::read_file(filename)?)?.with_wrap(Wrap::Horizontal);
+            let survey = survey(&map, rights, downs)?;
+            for ((right, down), trees) in &survey {
+                println!("right {:>3} down {:>3}: {} trees", right, down, trees);
+            }
+            if let Some(((right, down), trees)) = fewest_trees(&survey) {
+                println!("fewest: right {} down {}, {} trees", right, down, trees);
+            }
+            Ok(())
+        }
+        _ => Err(anyhow!(USAGE)),
+    }
+}
@@ -1,10 +1,26 @@
 // ==== day 4
 
-use crate::utils;
-use anyhow::Result;
-use once_cell::sync::Lazy;
-use regex::Regex;
-use std::str::FromStr;
+use crate::utils::{self, ParseError, Record};
+use anyhow::{anyhow, Context, Result};
+use schema::Schema;
+use std::{collections::BTreeMap, convert::TryFrom, str::FromStr};
+use typed::ValidatedPassport;
+
+mod export;
+mod report;
+mod schema;
+mod typed;
+
+// the fields a passport may have, in the order they're written out
+const KNOWN_FIELDS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+// an attribute which isn't simply one of the known fields
+#[derive(Clone, Debug, PartialEq)]
+enum Extra {
+    Unknown(String),   // value of an unrecognised attribute
+    Duplicate(String), // value of a repeated known attribute (the first is kept)
+    Malformed,         // a token which isn't "name:value", kept whole as the key
+}
 
 #[derive(Default)]
 struct Passport {
@@ -16,147 +32,332 @@ struct Passport {
     ecl: Option<String>,
     pid: Option<String>,
     cid: Option<String>,
+    extras: BTreeMap<String, Vec<Extra>>, // by attribute name
 }
 
-static HGT_RE: Lazy<regex::Regex> =
-    Lazy::new(|| Regex::new(r"^[0-9]+(cm|in)$").expect("regex compilation failure"));
-static HCL_RE: Lazy<regex::Regex> =
-    Lazy::new(|| Regex::new(r"^#[0-9a-f]{6}$").expect("regex compilation failure"));
-static ECL_RE: Lazy<regex::Regex> = Lazy::new(|| {
-    Regex::new(r"^(amb|blu|brn|gry|grn|hzl|oth)$").expect("regex compilation failure")
-});
-static PID_RE: Lazy<regex::Regex> =
-    Lazy::new(|| Regex::new(r"^[0-9]{9}$").expect("regex compilation failure"));
-static PASSPORT_RE: Lazy<regex::Regex> = Lazy::new(|| {
-    Regex::new(r"(byr|iyr|eyr|hgt|hcl|ecl|pid|cid):(\S*)").expect("regex compilation failure")
-});
-
 impl Passport {
-    // true if all the required fields (valid or otherwise) are present (i.e. all but "cid")
-    fn required_fields_present(&self) -> bool {
-        self.byr.is_some()
-            && self.iyr.is_some()
-            && self.eyr.is_some()
-            && self.hgt.is_some()
-            && self.hcl.is_some()
-            && self.ecl.is_some()
-            && self.pid.is_some()
+    fn known_field_mut(&mut self, name: &str) -> Option<&mut Option<String>> {
+        match name {
+            "byr" => Some(&mut self.byr),
+            "iyr" => Some(&mut self.iyr),
+            "eyr" => Some(&mut self.eyr),
+            "hgt" => Some(&mut self.hgt),
+            "hcl" => Some(&mut self.hcl),
+            "ecl" => Some(&mut self.ecl),
+            "pid" => Some(&mut self.pid),
+            "cid" => Some(&mut self.cid),
+            _ => None,
+        }
     }
 
-    // true if all the required fields are both present and valid
-    fn is_valid(&self) -> bool {
-        self.required_fields_present()
-            && self.is_byr_valid()
-            && self.is_iyr_valid()
-            && self.is_eyr_valid()
-            && self.is_hgt_valid()
-            && self.is_hcl_valid()
-            && self.is_ecl_valid()
-            && self.is_pid_valid()
-            && self.is_cid_valid()
+    // the value of a field by name, for the validation rules; unknown
+    // attributes can be checked too
+    fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "byr" => self.byr.as_deref(),
+            "iyr" => self.iyr.as_deref(),
+            "eyr" => self.eyr.as_deref(),
+            "hgt" => self.hgt.as_deref(),
+            "hcl" => self.hcl.as_deref(),
+            "ecl" => self.ecl.as_deref(),
+            "pid" => self.pid.as_deref(),
+            "cid" => self.cid.as_deref(),
+            _ => self.extras.get(name)?.iter().find_map(|e| match e {
+                Extra::Unknown(value) => Some(value.as_str()),
+                _ => None,
+            }),
+        }
     }
 
-    // byr (Birth Year) - four digits; at least 1920 and at most 2002.
-    fn is_byr_valid(&self) -> bool {
-        self.byr.as_ref().map_or(false, |s| {
-            utils::parse_usize_in_range(&s, 1920, 2002).is_some()
-        })
+    // a description of each extra attribute, in name order
+    fn irregularities(&self) -> Vec<String> {
+        self.extras
+            .iter()
+            .flat_map(|(name, extras)| {
+                extras.iter().map(move |e| match e {
+                    Extra::Unknown(value) => format!("unknown attribute '{}:{}'", name, value),
+                    Extra::Duplicate(value) => format!("duplicate attribute '{}:{}'", name, value),
+                    Extra::Malformed => format!("malformed attribute '{}'", name),
+                })
+            })
+            .collect()
     }
 
-    // iyr (Issue Year) - four digits; at least 2010 and at most 2020.
-    fn is_iyr_valid(&self) -> bool {
-        self.iyr.as_ref().map_or(false, |s| {
-            utils::parse_usize_in_range(&s, 2010, 2020).is_some()
-        })
+    // the extra attributes as batch tokens ("nat:fr", or a malformed token
+    // as it was), in name order
+    fn extra_tokens(&self) -> Vec<String> {
+        self.extras
+            .iter()
+            .flat_map(|(name, extras)| {
+                extras.iter().map(move |e| match e {
+                    Extra::Unknown(value) | Extra::Duplicate(value) => {
+                        format!("{}:{}", name, value)
+                    }
+                    Extra::Malformed => name.clone(),
+                })
+            })
+            .collect()
     }
+}
 
-    // eyr (Expiration Year) - four digits; at least 2020 and at most 2030.
-    fn is_eyr_valid(&self) -> bool {
-        self.eyr.as_ref().map_or(false, |s| {
-            utils::parse_usize_in_range(&s, 2020, 2030).is_some()
-        })
-    }
+// Anything which isn't a known field goes into extras rather than failing, so
+// a batch in a new format can still be read; see Strictness.
+impl FromStr for Passport {
+    type Err = anyhow::Error;
 
-    // hgt (Height) - a number followed by either cm or in:
-    //    If cm, the number must be at least 150 and at most 193.
-    //    If in, the number must be at least 59 and at most 76.
-    fn is_hgt_valid(&self) -> bool {
-        if let Some(s) = &self.hgt {
-            if HGT_RE.is_match(s) {
-                if s.ends_with("cm") {
-                    return utils::parse_usize_in_range(&s[..s.len() - 2], 150, 193).is_some();
-                } else if s.ends_with("in") {
-                    return utils::parse_usize_in_range(&s[..s.len() - 2], 59, 76).is_some();
+    fn from_str(s: &str) -> std::result::Result<Self, anyhow::Error> {
+        let mut ret: Self = Passport::default();
+        for token in s.split_whitespace() {
+            let (name, extra) = match token.split_once(':') {
+                Some((name, value))
+                    if !name.is_empty()
+                        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+                {
+                    match ret.known_field_mut(name) {
+                        Some(field @ None) => {
+                            *field = Some(value.to_string());
+                            continue;
+                        }
+                        Some(Some(_)) => (name, Extra::Duplicate(value.to_string())),
+                        None => (name, Extra::Unknown(value.to_string())),
+                    }
                 }
-            }
+                _ => (token, Extra::Malformed),
+            };
+            ret.extras.entry(name.to_string()).or_default().push(extra);
         }
-        false
+        Ok(ret)
     }
+}
 
-    // hcl (Hair Color) - a # followed by exactly six characters 0-9 or a-f.
-    fn is_hcl_valid(&self) -> bool {
-        self.hcl.as_ref().map_or(false, |s| HCL_RE.is_match(s))
-    }
+// what to do about a passport with extra attributes
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Strictness {
+    Ignore, // keep them in extras and carry on
+    Warn,   // carry on, but say so on stderr
+    Reject, // fail to parse the batch
+}
 
-    // ecl (Eye Color) - exactly one of: amb blu brn gry grn hzl oth.
-    fn is_ecl_valid(&self) -> bool {
-        self.ecl.as_ref().map_or(false, |s| ECL_RE.is_match(s))
-    }
+impl FromStr for Strictness {
+    type Err = anyhow::Error;
 
-    // pid (Passport ID) - a nine-digit number, including leading zeroes.
-    fn is_pid_valid(&self) -> bool {
-        self.pid.as_ref().map_or(false, |s| PID_RE.is_match(s))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(Strictness::Ignore),
+            "warn" => Ok(Strictness::Warn),
+            "reject" => Ok(Strictness::Reject),
+            _ => Err(anyhow!("expected ignore, warn or reject, found '{}'", s)),
+        }
     }
+}
 
-    // cid (Country ID) - ignored, missing or not.
-    #[allow(clippy::unused_self)]
-    fn is_cid_valid(&self) -> bool {
-        true
+// parse each blank-line separated passport of a batch, with its record
+fn parse_batch(s: &str, strictness: Strictness) -> Result<Vec<(Record<'_>, Passport)>> {
+    let mut ret = Vec::new();
+    for record in utils::records(s) {
+        let passport: Passport = record
+            .text
+            .parse()
+            .map_err(|e| ParseError::new(record.first_line, e))?;
+        for problem in passport.irregularities() {
+            let lines = match record.last_line - record.first_line {
+                0 => format!("line {}", record.first_line),
+                _ => format!("lines {}-{}", record.first_line, record.last_line),
+            };
+            match strictness {
+                Strictness::Ignore => (),
+                Strictness::Warn => eprintln!("warning: passport at {}: {}", lines, problem),
+                Strictness::Reject => {
+                    return Err(ParseError::new(record.first_line, problem).into());
+                }
+            }
+        }
+        ret.push((record, passport));
     }
+    Ok(ret)
 }
 
-impl FromStr for Passport {
-    type Err = anyhow::Error;
+// (passports with every required field, passports which pass every rule)
+fn count_valid(passports: &[Passport], schema: &Schema) -> (usize, usize) {
+    passports
+        .iter()
+        .map(|p| schema.validate(p))
+        .fold((0, 0), |(complete, valid), failures| {
+            let missing = failures
+                .iter()
+                .any(|f| matches!(f, schema::Failure::Missing(_)));
+            (
+                complete + usize::from(!missing),
+                valid + usize::from(failures.is_empty()),
+            )
+        })
+}
 
-    fn from_str(s: &str) -> std::result::Result<Self, anyhow::Error> {
-        let mut ret: Self = Passport::default();
-        s.split_whitespace().for_each(|s| {
-            PASSPORT_RE.captures_iter(&s).for_each(|cap| match &cap[1] {
-                "byr" => ret.byr = Some(cap[2].to_string()),
-                "iyr" => ret.iyr = Some(cap[2].to_string()),
-                "eyr" => ret.eyr = Some(cap[2].to_string()),
-                "hgt" => ret.hgt = Some(cap[2].to_string()),
-                "hcl" => ret.hcl = Some(cap[2].to_string()),
-                "ecl" => ret.ecl = Some(cap[2].to_string()),
-                "cid" => ret.cid = Some(cap[2].to_string()),
-                "pid" => ret.pid = Some(cap[2].to_string()),
-                _ => panic!("warning: unknown attribute: {}:{}", &cap[1], &cap[2]),
-            });
-        });
-        Ok(ret)
-    }
+// the passports which pass every rule, as typed passports
+fn validated(passports: Vec<Passport>, schema: &Schema) -> Result<Vec<ValidatedPassport>> {
+    passports
+        .into_iter()
+        .enumerate()
+        .filter(|(_, p)| schema.validate(p).is_empty())
+        .map(|(i, p)| ValidatedPassport::try_from(p).with_context(|| format!("passport {}", i + 1)))
+        .collect()
 }
 
 pub fn run() -> Result<(usize, usize)> {
     let data = utils::read_file("data/input-04.txt")?;
     let answers = utils::read_i64s("data/output-04.txt")?;
+    let schema = Schema::load("data/passport-rules.txt")?;
 
     // parse into an array of Passport{}
-    let passports: Vec<Passport> = data
-        .split("\n\n")
-        .map(|s| Passport::from_str(&s).expect("error parsing passport"))
+    let passports: Vec<Passport> = parse_batch(&data, Strictness::Reject)?
+        .into_iter()
+        .map(|(_, p)| p)
         .collect();
 
-    // count passports with all req'd fields
-    let p1 = passports
-        .iter()
-        .filter(|p| p.required_fields_present())
-        .count();
+    // count passports with all req'd fields, and with all req'd and
+    // validated fields
+    let (p1, p2) = count_valid(&passports, &schema);
     assert_eq!(p1, answers[0] as usize);
-
-    // count passports with all req'd and validated fields
-    let p2 = passports.iter().filter(|p| p.is_valid()).count();
     assert_eq!(p2, answers[1] as usize);
 
+    // every valid passport has well formed fields
+    assert_eq!(validated(passports, &schema)?.len(), p2);
+
     Ok((p1, p2))
 }
+
+// Command line options: the "--" flags a command accepts, "--extras MODE" for
+// any command, and the remaining positional arguments.
+struct Options<'a> {
+    flags: Vec<&'a str>,
+    extras: Strictness,
+    args: Vec<&'a str>,
+}
+
+impl<'a> Options<'a> {
+    fn parse(args: &'a [String], allowed: &[&str]) -> Result<Self> {
+        let mut options = Options {
+            flags: Vec::new(),
+            extras: Strictness::Warn,
+            args: Vec::new(),
+        };
+        let mut args = args.iter().map(String::as_str);
+        while let Some(arg) = args.next() {
+            match arg {
+                "--extras" => {
+                    options.extras = args
+                        .next()
+                        .ok_or_else(|| anyhow!("--extras needs ignore, warn or reject"))?
+                        .parse()?
+                }
+                flag if allowed.contains(&flag) => options.flags.push(flag),
+                flag if flag.starts_with("--") => return Err(anyhow!("unknown option {}", flag)),
+                arg => options.args.push(arg),
+            }
+        }
+        Ok(options)
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    // FILE [RULES]: the batch's text, and the rules to check it against
+    fn batch_and_rules(&self) -> Result<(String, Schema)> {
+        let (filename, rules) = match self.args.as_slice() {
+            [filename] => (*filename, "data/passport-rules.txt"),
+            [filename, rules] => (*filename, *rules),
+            _ => return Err(anyhow!("expected FILE [RULES]")),
+        };
+        Ok((utils::read_file(filename)?, Schema::load(rules)?))
+    }
+}
+
+// command line tools for passport batches
+pub fn cli(args: &[String]) -> Result<()> {
+    match args {
+        [cmd, rest @ ..] if cmd == "validate" => {
+            // report on a batch checked against the default rules, or a rules
+            // file, as text or JSON
+            let options = Options::parse(rest, &["--json"])?;
+            let (data, schema) = options.batch_and_rules()?;
+            let batch = parse_batch(&data, options.extras)?;
+            let report = report::Report::new(&batch, &schema);
+            if options.has("--json") {
+                print!("{}", report.to_json()?);
+            } else {
+                print!("{}", report);
+            }
+            Ok(())
+        }
+        [cmd, rest @ ..] if cmd == "normalise" => {
+            // print the valid passports with their fields in a fixed order,
+            // and with --metric, every height in centimetres
+            let options = Options::parse(rest, &["--metric"])?;
+            let (data, schema) = options.batch_and_rules()?;
+            let passports = parse_batch(&data, options.extras)?
+                .into_iter()
+                .map(|(_, p)| p)
+                .collect();
+            for mut p in validated(passports, &schema)? {
+                if options.has("--metric") {
+                    p.hgt = p.hgt.metric();
+                }
+                println!("{}\n", p);
+            }
+            Ok(())
+        }
+        [cmd, rest @ ..] if cmd == "export" => {
+            // every passport as CSV or JSON Lines, with whether it's valid
+            let (format, rest) = rest
+                .split_first()
+                .ok_or_else(|| anyhow!("expected csv or jsonl"))?;
+            let options = Options::parse(rest, &[])?;
+            let (data, schema) = options.batch_and_rules()?;
+            let batch = parse_batch(&data, options.extras)?;
+            match format.as_str() {
+                "csv" => print!("{}", export::to_csv(&batch, &schema)),
+                "jsonl" => print!("{}", export::to_json_lines(&batch, &schema)),
+                _ => return Err(anyhow!("unknown format '{}': use csv or jsonl", format)),
+            }
+            Ok(())
+        }
+        [cmd, filename] if cmd == "import" => {
+            // a CSV export back to the batch format
+            let data = utils::read_file(filename)?;
+            print!(
+                "{}",
+                export::csv_to_batch(&data).with_context(|| filename.clone())?
+            );
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "usage: day04 validate [--json] FILE [RULES] | day04 normalise [--metric] FILE [RULES]\n       \
+             day04 export csv|jsonl FILE [RULES] | day04 import FILE.csv\n       \
+             (all but import take --extras ignore|warn|reject for attributes which aren't known fields)"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_cid_is_valid() {
+        let schema = Schema::load("data/passport-rules.txt").unwrap();
+        let batch =
+            "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704 cid:abc\n\n\
+                     byr:1989 iyr:2014 eyr:2029 hgt:165cm hcl:#a97842 ecl:blu pid:896056539\n";
+        let passports: Vec<Passport> = parse_batch(batch, Strictness::Reject)
+            .unwrap()
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        let typed = validated(passports, &schema).unwrap();
+        assert_eq!(typed.len(), 2);
+        assert_eq!(typed[0].cid.as_deref(), Some("abc"));
+        assert_eq!(typed[1].cid, None);
+        assert!(typed[0].to_string().ends_with(" cid:abc"));
+    }
+}
@@ -0,0 +1,136 @@
+// ==== day 4: CSV and JSON Lines export, and CSV import
+//
+// Every format has the known fields in a fixed order, then any extra
+// attributes as batch tokens ("nat:fr bogus"), then whether the passport
+// passes the rules. Importing ignores the validity column, so a batch can be
+// exported, edited in a spreadsheet and imported back.
+
+use super::{schema::Schema, Passport, KNOWN_FIELDS};
+use crate::utils::{self, csv_line, json_string, ParseError, Record};
+use anyhow::Result;
+
+const EXTRAS: &str = "extras";
+const VALID: &str = "valid";
+
+pub fn to_csv(batch: &[(Record, Passport)], schema: &Schema) -> String {
+    let mut header: Vec<&str> = KNOWN_FIELDS.to_vec();
+    header.extend([EXTRAS, VALID]);
+    let header: Vec<Option<&str>> = header.into_iter().map(Some).collect();
+    let mut s = csv_line(&header) + "\n";
+    for (_, p) in batch {
+        // a missing field is an empty cell, and an empty one is ""
+        let mut row: Vec<Option<String>> = KNOWN_FIELDS
+            .iter()
+            .map(|f| p.field(f).map(String::from))
+            .collect();
+        let extras = p.extra_tokens();
+        row.push(Some(extras.join(" ")).filter(|_| !extras.is_empty()));
+        row.push(Some(schema.validate(p).is_empty().to_string()));
+        s += &csv_line(&row);
+        s.push('\n');
+    }
+    s
+}
+
+// one JSON object per line, with null for missing fields
+pub fn to_json_lines(batch: &[(Record, Passport)], schema: &Schema) -> String {
+    let mut s = String::new();
+    for (_, p) in batch {
+        let mut members: Vec<String> = KNOWN_FIELDS
+            .iter()
+            .map(|f| {
+                let value = p.field(f).map_or("null".to_string(), json_string);
+                format!("{}:{}", json_string(f), value)
+            })
+            .collect();
+        let extras: Vec<String> = p.extra_tokens().iter().map(|t| json_string(t)).collect();
+        members.push(format!("{}:[{}]", json_string(EXTRAS), extras.join(",")));
+        members.push(format!(
+            "{}:{}",
+            json_string(VALID),
+            schema.validate(p).is_empty()
+        ));
+        s += &format!("{{{}}}\n", members.join(","));
+    }
+    s
+}
+
+// CSV with a header row back to the blank-line separated batch format. The
+// columns may come in any order; columns which aren't known fields (other than
+// extras and valid) become attributes too. Empty cells are left out, while a
+// quoted empty cell ("") is a field with an empty value. Names and values
+// can't contain whitespace, which would split them into separate attributes;
+// only the extras cell is a whitespace separated list.
+pub fn csv_to_batch(s: &str) -> Result<String, ParseError> {
+    let mut lines = s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (header_line, header) = match lines.next() {
+        Some((i, l)) => (i + 1, utils::parse_csv_line(l).map_err(|e| e.offset(i))?),
+        None => return Ok(String::new()),
+    };
+    let mut names = Vec::new();
+    for (column, name) in header {
+        let name = name.unwrap_or_default();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(ParseError::new(
+                header_line,
+                format!("column name '{}' must be a single word", name),
+            )
+            .with_column(column));
+        }
+        names.push(name);
+    }
+    let mut passports = Vec::new();
+    for (i, l) in lines {
+        let row = utils::parse_csv_line(l).map_err(|e| e.offset(i))?;
+        if row.len() != names.len() {
+            return Err(ParseError::new(
+                i + 1,
+                format!("expected {} cells, found {}", names.len(), row.len()),
+            ));
+        }
+        let mut tokens: Vec<String> = Vec::new();
+        for (name, (column, cell)) in names.iter().zip(&row) {
+            let cell = match cell {
+                Some(cell) => cell,
+                None => continue,
+            };
+            match name.as_str() {
+                VALID => (),
+                EXTRAS => tokens.extend(cell.split_whitespace().map(String::from)),
+                _ if cell.contains(char::is_whitespace) => {
+                    return Err(ParseError::new(
+                        i + 1,
+                        format!("{} '{}' contains whitespace", name, cell),
+                    )
+                    .with_column(*column))
+                }
+                name => tokens.push(format!("{}:{}", name, cell)),
+            }
+        }
+        passports.push(tokens.join(" "));
+    }
+    let mut batch = passports.join("\n\n");
+    if !batch.is_empty() {
+        batch.push('\n');
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_batch, Strictness};
+    use super::*;
+
+    #[test]
+    fn empty_fields_survive_csv() {
+        let schema = Schema::load("data/passport-rules.txt").unwrap();
+        let batch = "byr:1980 hgt: ecl:grn nat:fr\n\npid:087499704 cid:\n";
+        let csv = to_csv(&parse_batch(batch, Strictness::Ignore).unwrap(), &schema);
+        assert_eq!(csv.lines().nth(1), Some("1980,,,\"\",,grn,,,nat:fr,false"));
+        let imported = csv_to_batch(&csv).unwrap();
+        assert_eq!(
+            imported,
+            "byr:1980 hgt: ecl:grn nat:fr\n\npid:087499704 cid:\n"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+// ==== day 4: validation report
+//
+// Which passports in a batch fail the rules, where they are in the file, and
+// why; with counts of each reason across the batch.
+
+use super::{
+    schema::{Failure, Schema},
+    Passport,
+};
+use crate::utils::{json_string, Record};
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+// one failing passport
+pub struct Entry {
+    pub index: usize, // 1-based record number in the batch
+    pub first_line: usize,
+    pub last_line: usize,
+    pub missing: Vec<String>, // required fields which are absent
+    pub invalid: Vec<(String, String, String)>, // (field, value, rule which rejected it)
+}
+
+pub struct Report {
+    pub total: usize,
+    pub complete: usize, // passports with every required field
+    pub valid: usize,    // passports which pass every rule
+    pub failures: Vec<Entry>,
+    pub reasons: BTreeMap<String, usize>, // e.g. "missing ecl" => 12
+}
+
+impl Report {
+    // check every passport of a batch
+    pub fn new(batch: &[(Record, Passport)], schema: &Schema) -> Self {
+        let mut report = Report {
+            total: 0,
+            complete: 0,
+            valid: 0,
+            failures: Vec::new(),
+            reasons: BTreeMap::new(),
+        };
+        for (i, (record, passport)) in batch.iter().enumerate() {
+            let mut entry = Entry {
+                index: i + 1,
+                first_line: record.first_line,
+                last_line: record.last_line,
+                missing: Vec::new(),
+                invalid: Vec::new(),
+            };
+            for failure in schema.validate(passport) {
+                let reason = match &failure {
+                    Failure::Missing(rule) => {
+                        entry.missing.push(rule.field.clone());
+                        format!("missing {}", rule.field)
+                    }
+                    Failure::Invalid(rule, value) => {
+                        let check = rule.check.to_string();
+                        entry
+                            .invalid
+                            .push((rule.field.clone(), value.clone(), check.clone()));
+                        format!("invalid {} ({})", rule.field, check)
+                    }
+                };
+                *report.reasons.entry(reason).or_insert(0) += 1;
+            }
+            report.total += 1;
+            report.complete += usize::from(entry.missing.is_empty());
+            if entry.missing.is_empty() && entry.invalid.is_empty() {
+                report.valid += 1;
+            } else {
+                report.failures.push(entry);
+            }
+        }
+        report
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let mut s = String::from("{\"failures\":[\n");
+        for (i, e) in self.failures.iter().enumerate() {
+            let missing: Vec<String> = e.missing.iter().map(|f| json_string(f)).collect();
+            let invalid: Vec<String> = e
+                .invalid
+                .iter()
+                .map(|(field, value, rule)| {
+                    format!(
+                        r#"{{"field":{},"value":{},"rule":{}}}"#,
+                        json_string(field),
+                        json_string(value),
+                        json_string(rule)
+                    )
+                })
+                .collect();
+            write!(
+                s,
+                r#"  {{"index":{},"first_line":{},"last_line":{},"missing":[{}],"invalid":[{}]}}"#,
+                e.index,
+                e.first_line,
+                e.last_line,
+                missing.join(","),
+                invalid.join(",")
+            )?;
+            s += if i + 1 < self.failures.len() {
+                ",\n"
+            } else {
+                "\n"
+            };
+        }
+        let reasons: Vec<String> = self
+            .reasons
+            .iter()
+            .map(|(reason, n)| format!("{}:{}", json_string(reason), n))
+            .collect();
+        writeln!(
+            s,
+            r#"],"total":{},"complete":{},"valid":{},"reasons":{{{}}}}}"#,
+            self.total,
+            self.complete,
+            self.valid,
+            reasons.join(",")
+        )?;
+        Ok(s)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.failures {
+            let lines = match e.last_line - e.first_line {
+                0 => format!("line {}", e.first_line),
+                _ => format!("lines {}-{}", e.first_line, e.last_line),
+            };
+            let mut problems: Vec<String> =
+                e.missing.iter().map(|f| format!("missing {}", f)).collect();
+            problems.extend(
+                e.invalid.iter().map(|(field, value, rule)| {
+                    format!("invalid {} '{}' ({})", field, value, rule)
+                }),
+            );
+            writeln!(
+                f,
+                "passport {} ({}): {}",
+                e.index,
+                lines,
+                problems.join("; ")
+            )?;
+        }
+        writeln!(
+            f,
+            "{} passports: {} with every required field, {} valid",
+            self.total, self.complete, self.valid
+        )?;
+        if !self.reasons.is_empty() {
+            writeln!(f, "failures by reason:")?;
+            for (reason, n) in &self.reasons {
+                writeln!(f, "{:>6}  {}", n, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
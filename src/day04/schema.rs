@@ -0,0 +1,165 @@
+// ==== day 4: passport validation rules
+//
+// The rules live in a small text file (see data/passport-rules.txt), one field
+// per line, so a policy change needs no recompile. Each rule says whether the
+// field is required and how to check its value.
+
+use super::Passport;
+use crate::utils::{self, ParseError};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::{fmt, str::FromStr};
+
+pub enum Check {
+    Any,
+    Int(i64, i64),                  // an integer in this range (inclusive)
+    Units(Vec<(String, i64, i64)>), // an integer in the range for its unit suffix
+    Regex(String, Regex),           // the pattern as written, and anchored
+    Enum(Vec<String>),              // one of these values
+}
+
+fn parse_int(s: &str) -> Result<i64> {
+    s.parse()
+        .map_err(|_| anyhow!("expected an integer, found '{}'", s))
+}
+
+impl FromStr for Check {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, args) = s.split_once(' ').unwrap_or((s, ""));
+        let args = args.trim();
+        let words: Vec<&str> = args.split_whitespace().collect();
+        match (kind, words.as_slice()) {
+            ("any", []) => Ok(Check::Any),
+            ("int", [min, max]) => Ok(Check::Int(parse_int(min)?, parse_int(max)?)),
+            ("units", _) => args
+                .split(',')
+                .map(
+                    |u| match u.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                        [unit, min, max] => {
+                            Ok((unit.to_string(), parse_int(min)?, parse_int(max)?))
+                        }
+                        _ => Err(anyhow!("expected 'UNIT MIN MAX', found '{}'", u.trim())),
+                    },
+                )
+                .collect::<Result<_>>()
+                .map(Check::Units),
+            ("regex", [_, ..]) => Ok(Check::Regex(
+                args.to_string(),
+                Regex::new(&format!("^(?:{})$", args))?,
+            )),
+            ("enum", [_, ..]) => Ok(Check::Enum(words.iter().map(|w| w.to_string()).collect())),
+            ("any", _) | ("int", _) | ("regex", _) | ("enum", _) => {
+                Err(anyhow!("wrong arguments for '{}': '{}'", kind, args))
+            }
+            _ => Err(anyhow!("unknown check '{}'", kind)),
+        }
+    }
+}
+
+// writes the rules file syntax
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Any => write!(f, "any"),
+            Check::Int(min, max) => write!(f, "int {} {}", min, max),
+            Check::Units(units) => {
+                let units: Vec<String> = units
+                    .iter()
+                    .map(|(unit, min, max)| format!("{} {} {}", unit, min, max))
+                    .collect();
+                write!(f, "units {}", units.join(", "))
+            }
+            Check::Regex(pattern, _) => write!(f, "regex {}", pattern),
+            Check::Enum(values) => write!(f, "enum {}", values.join(" ")),
+        }
+    }
+}
+
+impl Check {
+    pub fn accepts(&self, value: &str) -> bool {
+        let in_range =
+            |s: &str, min: i64, max: i64| s.parse().is_ok_and(|n: i64| min <= n && n <= max);
+        match self {
+            Check::Any => true,
+            Check::Int(min, max) => in_range(value, *min, *max),
+            Check::Units(units) => units.iter().any(|(unit, min, max)| {
+                value.strip_suffix(unit.as_str()).is_some_and(|n| {
+                    n.bytes().all(|b| b.is_ascii_digit()) && in_range(n, *min, *max)
+                })
+            }),
+            Check::Regex(_, re) => re.is_match(value),
+            Check::Enum(values) => values.iter().any(|v| v == value),
+        }
+    }
+}
+
+pub struct FieldRule {
+    pub field: String,
+    pub required: bool,
+    pub check: Check,
+}
+
+// why a passport fails a schema
+pub enum Failure<'a> {
+    Missing(&'a FieldRule),
+    Invalid(&'a FieldRule, String), // the rule, and the value it rejected
+}
+
+pub struct Schema {
+    pub rules: Vec<FieldRule>,
+}
+
+impl FromStr for Schema {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rules: Vec<Option<FieldRule>> = utils::parse_lines_with(s, |l| {
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('#') {
+                return Ok(None);
+            }
+            let mut words = l.splitn(3, ' ');
+            let field = words.next().unwrap().to_string();
+            let required = match words.next() {
+                Some("required") => true,
+                Some("optional") => false,
+                _ => {
+                    return Err(anyhow!(
+                        "expected 'required' or 'optional' after '{}'",
+                        field
+                    ))
+                }
+            };
+            let check = words.next().unwrap_or("any").trim().parse()?;
+            Ok(Some(FieldRule {
+                field,
+                required,
+                check,
+            }))
+        })?;
+        Ok(Schema {
+            rules: rules.into_iter().flatten().collect(),
+        })
+    }
+}
+
+impl Schema {
+    pub fn load(filename: &str) -> Result<Self> {
+        Ok(utils::read_file(filename)?.parse()?)
+    }
+
+    // every way the passport breaks the rules, in rule order
+    pub fn validate<'a>(&'a self, passport: &Passport) -> Vec<Failure<'a>> {
+        self.rules
+            .iter()
+            .filter_map(|rule| match passport.field(&rule.field) {
+                None if rule.required => Some(Failure::Missing(rule)),
+                None => None,
+                Some(value) if rule.check.accepts(value) => None,
+                Some(value) => Some(Failure::Invalid(rule, value.to_string())),
+            })
+            .collect()
+    }
+}
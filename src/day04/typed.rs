@@ -0,0 +1,199 @@
+// ==== day 4: typed passports
+//
+// A passport whose fields have been parsed into proper types, so nothing
+// downstream handles strings. Conversion checks that each field is present
+// (except cid) and well formed; the ranges and other policy belong to the
+// validation rules in schema.rs.
+
+use super::Passport;
+use anyhow::{anyhow, Result};
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Cm(u32),
+    In(u32),
+}
+
+impl Length {
+    pub fn to_cm(self) -> f64 {
+        match self {
+            Length::Cm(n) => f64::from(n),
+            Length::In(n) => f64::from(n) * 2.54,
+        }
+    }
+
+    // the same length in whole centimetres
+    pub fn metric(self) -> Length {
+        Length::Cm(self.to_cm().round() as u32)
+    }
+}
+
+impl FromStr for Length {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| -> Result<u32> {
+            if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(anyhow!("expected a number before the unit in '{}'", s));
+            }
+            Ok(n.parse()?)
+        };
+        if let Some(n) = s.strip_suffix("cm") {
+            Ok(Length::Cm(number(n)?))
+        } else if let Some(n) = s.strip_suffix("in") {
+            Ok(Length::In(number(n)?))
+        } else {
+            Err(anyhow!("expected a length in cm or in, found '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Cm(n) => write!(f, "{}cm", n),
+            Length::In(n) => write!(f, "{}in", n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    // "#rrggbb", in lowercase hex
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|h| h.len() == 6 && h.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')))
+            .ok_or_else(|| anyhow!("expected a colour like #1a2b3c, found '{}'", s))?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EyeColour {
+    Amber,
+    Blue,
+    Brown,
+    Grey,
+    Green,
+    Hazel,
+    Other,
+}
+
+const EYE_COLOURS: [(&str, EyeColour); 7] = [
+    ("amb", EyeColour::Amber),
+    ("blu", EyeColour::Blue),
+    ("brn", EyeColour::Brown),
+    ("gry", EyeColour::Grey),
+    ("grn", EyeColour::Green),
+    ("hzl", EyeColour::Hazel),
+    ("oth", EyeColour::Other),
+];
+
+impl FromStr for EyeColour {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EYE_COLOURS
+            .iter()
+            .find(|(code, _)| *code == s)
+            .map(|(_, colour)| *colour)
+            .ok_or_else(|| anyhow!("unknown eye colour '{}'", s))
+    }
+}
+
+impl fmt::Display for EyeColour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (code, _) = EYE_COLOURS.iter().find(|(_, c)| c == self).unwrap();
+        write!(f, "{}", code)
+    }
+}
+
+// nine digits, leading zeroes included
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassportId(pub u32);
+
+impl FromStr for PassportId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 9 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(anyhow!("expected a nine digit passport id, found '{}'", s));
+        }
+        Ok(PassportId(s.parse()?))
+    }
+}
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:09}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatedPassport {
+    pub byr: u32,
+    pub iyr: u32,
+    pub eyr: u32,
+    pub hgt: Length,
+    pub hcl: Rgb,
+    pub ecl: EyeColour,
+    pub pid: PassportId,
+    pub cid: Option<String>, // any value, so kept as it was written
+}
+
+// parse a field, naming it in any error
+fn field<T>(name: &str, value: Option<&str>) -> Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let value = value.ok_or_else(|| anyhow!("missing {}", name))?;
+    value
+        .parse()
+        .map_err(|e| anyhow!("bad {} '{}': {}", name, value, e))
+}
+
+impl TryFrom<Passport> for ValidatedPassport {
+    type Error = anyhow::Error;
+
+    fn try_from(p: Passport) -> Result<Self, Self::Error> {
+        Ok(ValidatedPassport {
+            byr: field("byr", p.field("byr"))?,
+            iyr: field("iyr", p.field("iyr"))?,
+            eyr: field("eyr", p.field("eyr"))?,
+            hgt: field("hgt", p.field("hgt"))?,
+            hcl: field("hcl", p.field("hcl"))?,
+            ecl: field("ecl", p.field("ecl"))?,
+            pid: field("pid", p.field("pid"))?,
+            cid: p.field("cid").map(String::from),
+        })
+    }
+}
+
+// the batch format, with fields in a fixed order
+impl fmt::Display for ValidatedPassport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.byr, self.iyr, self.eyr, self.hgt, self.hcl, self.ecl, self.pid
+        )?;
+        if let Some(cid) = &self.cid {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
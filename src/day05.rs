@@ -1,41 +1,46 @@
 // ==== day 5
 
 use crate::utils;
-use anyhow::Result;
-
-fn seat_code_to_usize(code: &str) -> usize {
-    assert_eq!(code.len(), 10);
-    let bincode = str::replace(code, "B", "1");
-    let bincode = str::replace(&bincode, "F", "0");
-    let bincode = str::replace(&bincode, "R", "1");
-    let bincode = str::replace(&bincode, "L", "0");
-    usize::from_str_radix(&bincode, 2).unwrap()
+use anyhow::{anyhow, Result};
+
+fn seat_code_to_usize(code: &str) -> Result<usize> {
+    if code.len() != 10 {
+        return Err(anyhow!("seat code '{}' is not 10 characters", code));
+    }
+    code.chars().try_fold(0, |x, c| match c {
+        'B' | 'R' => Ok((x << 1) | 1),
+        'F' | 'L' => Ok(x << 1),
+        _ => Err(anyhow!("bad seat code character '{}'", c)),
+    })
 }
 
 fn usize_to_seat_id(x: usize) -> usize {
     ((x >> 3) * 8) + (x & 0b111)
 }
 
+// find the highest seat id, and the single empty seat between two occupied
+// ones; a seat code is 10 bits, so a table of every possible seat is enough
+// to stream any number of boarding passes
+fn find_seats(seats: impl Iterator<Item = usize>) -> Result<(usize, usize)> {
+    let mut occupied = [false; 1 << 10];
+    seats.for_each(|s| occupied[s] = true);
+
+    let highest = (0..occupied.len())
+        .rev()
+        .find(|s| occupied[*s])
+        .ok_or_else(|| anyhow!("no seats found"))?;
+    let empty = (1..occupied.len() - 1)
+        .find(|s| !occupied[*s] && occupied[s - 1] && occupied[s + 1])
+        .ok_or_else(|| anyhow!("no empty seat found"))?;
+    Ok((usize_to_seat_id(highest), usize_to_seat_id(empty)))
+}
+
 pub fn run() -> Result<(usize, usize)> {
-    let data = utils::read_file("data/input-05.txt")?;
     let answers = utils::read_i64s("data/output-05.txt")?;
 
-    // compute all seats (as integers)
-    let mut seat_ints: Vec<usize> = data.lines().map(|code| seat_code_to_usize(code)).collect();
-    seat_ints.sort_unstable();
-
-    // find the seat with the highest seat code
-    let p1 = usize_to_seat_id(*seat_ints.last().unwrap());
-
-    // find the empty seat
-    let mut empty = 0;
-    for i in 0..seat_ints.len() - 1 {
-        if seat_ints[i] + 2 == seat_ints[i + 1] {
-            empty = seat_ints[i] + 1;
-            break;
-        }
-    }
-    let p2 = usize_to_seat_id(empty);
+    // compute all seats (as integers) and search them
+    let seats = utils::stream_parsed_with("data/input-05.txt", seat_code_to_usize)?;
+    let (p1, p2) = utils::try_stream(seats, find_seats)?;
 
     assert_eq!(p1, answers[0] as usize);
     assert_eq!(p2, answers[1] as usize);
@@ -33,8 +33,9 @@ pub fn run() -> Result<(usize, usize)> {
     let data = utils::read_file("data/input-06.txt")?;
     let answers = utils::read_i64s("data/output-06.txt")?;
 
-    let p1 = data.split("\n\n").map(|s| count_uniq_alpha_chars(s)).sum();
-    let p2 = data.split("\n\n").map(|s| count_common_answers(s)).sum();
+    let groups = utils::records(&data);
+    let p1 = groups.iter().map(|g| count_uniq_alpha_chars(g.text)).sum();
+    let p2 = groups.iter().map(|g| count_common_answers(g.text)).sum();
 
     assert_eq!(p1, answers[0] as usize);
     assert_eq!(p2, answers[1] as usize);
@@ -1,8 +1,12 @@
 // ==== day 7
 
-use crate::utils;
-use anyhow::Result;
-use std::collections::HashMap;
+use crate::utils::{self, ParseError};
+use anyhow::{anyhow, Result};
+use graph::BagGraph;
+
+mod export;
+mod graph;
+mod query;
 
 // light red bags contain 1 bright white bag, 2 muted yellow bags.
 // dark orange bags contain 3 bright white bags, 4 muted yellow bags.
@@ -14,66 +18,165 @@ use std::collections::HashMap;
 // faded blue bags contain no other bags.
 // dotted black bags contain no other bags.
 
-struct PurseQuantity<'a>(usize, &'a str);
+peg::parser! {
+    grammar bag_rule_parser() for str {
+        // "bag" or "bags", as a whole word
+        rule bags() = "bag" "s"? !['a'..='z']
 
-// parse data into a HashMap<&str, Vec<PurseQuantity>>
-//
-// for example: "light red bags contain 1 bright white bag, 2 muted yellow bags."
-// becomes: { "light red": [(1, "bright white"), (2, "muted yellow")] }
-//
-fn parse(s: &str) -> HashMap<&str, Vec<PurseQuantity>> {
-    s.lines()
-        .map(|l| {
-            let parsed: Vec<&str> = l.splitn(2, " bags contain ").collect();
-            let container = parsed[0];
-            let contents: Vec<PurseQuantity> = match parsed[1].trim() {
-                "no other bags." => Vec::new(),
-                _ => parsed[1]
-                    .trim_end_matches('.')
-                    .split(',')
-                    .map(|c| {
-                        let trimmed = c.trim_end_matches("bags").trim_end_matches("bag").trim();
-                        let count: usize = trimmed[0..1].parse().unwrap();
-                        let purse = &trimmed[2..];
-                        PurseQuantity(count, purse)
-                    })
-                    .collect(),
-            };
-            (container, contents)
-        })
-        .collect()
-}
+        rule word() = !bags() ['a'..='z']+
+
+        // any number of words, e.g. "shiny gold"
+        rule colour() -> &'input str
+            = $(word() ++ " ")
+
+        rule quantity() -> usize
+            = n:$(['0'..='9']+) {?
+                match n.parse() {
+                    Ok(0) | Err(_) => Err("a positive quantity"),
+                    Ok(n) => Ok(n),
+                }
+            }
+
+        rule content() -> (usize, &'input str)
+            = n:quantity() " " c:colour() " " bags() { (n, c) }
 
-fn has_contents(data: &HashMap<&str, Vec<PurseQuantity>>, container: &str, contents: &str) -> bool {
-    let these_contents: &Vec<&str> = &data.get(&container).unwrap().iter().map(|c| c.1).collect();
-    these_contents.contains(&contents)
-        || these_contents
-            .iter()
-            .any(|m| has_contents(data, m, contents))
+        rule contents() -> Vec<(usize, &'input str)>
+            = "no other bags" { Vec::new() }
+            / content() ++ ", "
+
+        pub rule bag_rule() -> (&'input str, Vec<(usize, &'input str)>)
+            = c:colour() " " bags() " contain " contents:contents() "." { (c, contents) }
+    }
 }
 
-fn count_contents(data: &HashMap<&str, Vec<PurseQuantity>>, container: &str) -> usize {
-    let these_contents: &Vec<PurseQuantity> = &data.get(&container).unwrap();
-    these_contents
-        .iter()
-        .map(|c| c.0 + c.0 * count_contents(data, c.1))
-        .sum()
+// parse data into a BagGraph, skipping blank lines
+//
+// for example: "light red bags contain 1 bright white bag, 2 muted yellow bags."
+// adds the rule: light red => [(1, "bright white"), (2, "muted yellow")]
+//
+fn parse(s: &str) -> Result<BagGraph, ParseError> {
+    let mut graph = BagGraph::new();
+    for (i, l) in s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let (container, contents) = bag_rule_parser::bag_rule(l.trim_end()).map_err(|e| {
+            ParseError::new(i + 1, format!("expected {}", e.expected))
+                .with_column(e.location.column)
+        })?;
+        graph
+            .add_rule(container, &contents)
+            .map_err(|e| ParseError::new(i + 1, e))?;
+    }
+    Ok(graph)
 }
 
 pub fn run() -> Result<(usize, usize)> {
     let data = utils::read_file("data/input-07.txt")?;
     let answers = utils::read_i64s("data/output-07.txt")?;
 
-    let parsed = parse(&data);
+    let graph = parse(&data)?;
+    let shiny_gold = graph.id("shiny gold")?;
 
-    let p1 = parsed
-        .keys()
-        .filter(|k| has_contents(&parsed, k, "shiny gold"))
-        .count();
+    let p1 = graph.all_containers(shiny_gold).len();
 
-    let p2 = count_contents(&parsed, "shiny gold");
+    let p2 = graph.count_contents(shiny_gold)? as usize;
 
     assert_eq!(p1, answers[0] as usize);
     assert_eq!(p2, answers[1] as usize);
     Ok((p1, p2))
 }
+
+// command line tools for bag rules
+pub fn cli(args: &[String]) -> Result<()> {
+    match args {
+        [cmd, filename] if cmd == "order" => {
+            // every colour, outermost first, or the cycle which prevents that
+            let graph = parse(&utils::read_file(filename)?)?;
+            for id in graph.topological_order()? {
+                println!("{}", graph.name(id));
+            }
+            Ok(())
+        }
+        [cmd, filename] if cmd == "check" => {
+            let graph = parse(&utils::read_file(filename)?)?;
+            match graph.find_cycle() {
+                Some(cycle) => println!("cycle: {}", graph.describe_cycle(&cycle)),
+                None => println!("{} colours, no cycles", graph.len()),
+            }
+            Ok(())
+        }
+        [cmd, filename, colour] if cmd == "containers" => {
+            // which bags can eventually contain this colour
+            let graph = parse(&utils::read_file(filename)?)?;
+            let containers = graph.all_containers(graph.id(colour)?);
+            for id in &containers {
+                println!("{}", graph.name(*id));
+            }
+            println!("{} colours can contain {} bags", containers.len(), colour);
+            Ok(())
+        }
+        [cmd, filename, colour] if cmd == "count" => {
+            let graph = parse(&utils::read_file(filename)?)?;
+            let n = graph.count_contents(graph.id(colour)?)?;
+            println!("{} bags must contain {} other bags", colour, n);
+            Ok(())
+        }
+        [cmd, filename, from, to, rest @ ..] if cmd == "paths" && rest.len() <= 1 => {
+            // how `to` bags end up inside `from` bags
+            let graph = parse(&utils::read_file(filename)?)?;
+            let limit = match rest.first() {
+                Some(n) => n.parse()?,
+                None => 20,
+            };
+            let paths = graph.paths(graph.id(from)?, graph.id(to)?, limit)?;
+            for path in &paths.paths {
+                println!(
+                    "{} ({} bags)",
+                    graph.describe_path(path),
+                    path.multiplicity()
+                );
+            }
+            if paths.count > paths.paths.len() as u64 {
+                println!("... {} more", paths.count - paths.paths.len() as u64);
+            }
+            println!(
+                "{} paths: {} bags contain {} {} bags in total",
+                paths.count, from, paths.total, to
+            );
+            Ok(())
+        }
+        [cmd, filename, rest @ ..] if cmd == "depth" && rest.len() <= 1 => {
+            let graph = parse(&utils::read_file(filename)?)?;
+            match rest.first() {
+                Some(colour) => println!(
+                    "{} bags nest {} deep",
+                    colour,
+                    graph.depth(Some(graph.id(colour)?))?
+                ),
+                None => println!("bags nest at most {} deep", graph.depth(None)?),
+            }
+            Ok(())
+        }
+        [cmd, filename, rest @ ..] if cmd == "dot" && rest.len() <= 1 => {
+            let graph = parse(&utils::read_file(filename)?)?;
+            let query = match rest.first() {
+                Some(colour) => Some(graph.id(colour)?),
+                None => None,
+            };
+            print!("{}", export::to_dot(&graph, query));
+            Ok(())
+        }
+        [cmd, filename, colour, rest @ ..] if cmd == "tree" && rest.len() <= 1 => {
+            let graph = parse(&utils::read_file(filename)?)?;
+            let max_depth = match rest.first() {
+                Some(n) => Some(n.parse()?),
+                None => None,
+            };
+            print!("{}", export::to_tree(&graph, graph.id(colour)?, max_depth)?);
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "usage: day07 check FILE | day07 order FILE | day07 containers FILE COLOUR\n       \
+             day07 count FILE COLOUR | day07 paths FILE FROM TO [LIMIT] | day07 depth FILE [COLOUR]\n       \
+             day07 dot FILE [COLOUR] | day07 tree FILE COLOUR [DEPTH]"
+        )),
+    }
+}
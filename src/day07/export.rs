@@ -0,0 +1,95 @@
+// ==== day 7: Graphviz and text tree export of the bag rules
+
+use super::graph::{BagGraph, ColourId};
+use crate::utils::{Attrs, Digraph};
+use anyhow::Result;
+use std::fmt::Write;
+
+const QUERY: &str = "gold";
+const ANCESTOR: &str = "lightblue";
+const DESCENDANT: &str = "palegreen";
+
+// The containment graph, with an edge from each container to each of its
+// contents labelled with the quantity. When a query colour is given it's
+// filled in gold, the colours which can contain it in blue and the colours
+// it contains in green; the edges among each group are drawn bold.
+pub fn to_dot(graph: &BagGraph, query: Option<ColourId>) -> String {
+    let (above, below) = match query {
+        Some(id) => (graph.can_contain(id), graph.inside(id)),
+        None => (vec![false; graph.len()], vec![false; graph.len()]),
+    };
+    let fill = |c: ColourId| {
+        if query == Some(c) {
+            Some(QUERY)
+        } else if above[c] {
+            Some(ANCESTOR)
+        } else if below[c] {
+            Some(DESCENDANT)
+        } else {
+            None
+        }
+    };
+
+    let mut dot = Digraph::new("bags");
+    dot.graph_attr("rankdir", "LR");
+    dot.node_default("shape", "box");
+    dot.node_default("style", "rounded");
+    for c in 0..graph.len() {
+        let mut attrs: Attrs = Vec::new();
+        if let Some(colour) = fill(c) {
+            attrs.push(("style", "rounded,filled".to_string()));
+            attrs.push(("fillcolor", colour.to_string()));
+        }
+        dot.node(graph.name(c), attrs);
+    }
+    for c in 0..graph.len() {
+        for &(quantity, child) in graph.contents(c) {
+            let mut attrs: Attrs = vec![("label", quantity.to_string())];
+            let on_query_side = |x: ColourId| query == Some(x) || above[x];
+            let under_query = |x: ColourId| query == Some(x) || below[x];
+            if (on_query_side(c) && on_query_side(child)) || (under_query(c) && under_query(child))
+            {
+                attrs.push(("penwidth", "2".to_string()));
+            }
+            dot.edge(graph.name(c), graph.name(child), attrs);
+        }
+    }
+    dot.to_string()
+}
+
+// What a bag must contain, as an indented tree. Each line gives a quantity
+// and colour, and the bags they account for inside one of the bags on the
+// line above (themselves plus everything in them), which is the sum of the
+// subtotals of the lines below. Nesting deeper than max_depth is elided.
+pub fn to_tree(graph: &BagGraph, id: ColourId, max_depth: Option<usize>) -> Result<String> {
+    let totals = graph.count_all_contents(id)?;
+    let mut s = String::new();
+    writeln!(s, "{}: {} bags", graph.name(id), totals[id])?;
+
+    // depth first, with the index of the next child to visit
+    let mut stack: Vec<(ColourId, usize)> = vec![(id, 0)];
+    while let Some((c, next)) = stack.pop() {
+        if let Some(&(quantity, child)) = graph.contents(c).get(next) {
+            stack.push((c, next + 1));
+            let depth = stack.len();
+            let indent = "  ".repeat(depth);
+            let subtotal = quantity as u64 * (1 + totals[child]);
+            if max_depth.is_some_and(|max| depth > max) {
+                if next == 0 {
+                    writeln!(s, "{}...", indent)?;
+                }
+                continue;
+            }
+            writeln!(
+                s,
+                "{}{} {}: {} bags",
+                indent,
+                quantity,
+                graph.name(child),
+                subtotal
+            )?;
+            stack.push((child, 0));
+        }
+    }
+    Ok(s)
+}
@@ -0,0 +1,206 @@
+// ==== day 7: bag rule graph
+//
+// Colours are interned to dense IDs, and each rule becomes edges from the
+// container to its contents, weighted by quantity. Reverse edges are kept too,
+// so questions about containers are as cheap as questions about contents.
+// Every traversal is iterative and visits each colour and rule at most once.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+pub type ColourId = usize;
+
+#[derive(Default)]
+pub struct BagGraph {
+    names: Vec<String>,                      // colour name of each ID
+    ids: HashMap<String, ColourId>,          // ID of each colour name
+    has_rule: Vec<bool>,                     // whether a rule defined the colour
+    contents: Vec<Vec<(usize, ColourId)>>,   // (quantity, colour) directly inside
+    containers: Vec<Vec<(usize, ColourId)>>, // (quantity, colour) directly around
+}
+
+impl BagGraph {
+    pub fn new() -> Self {
+        BagGraph::default()
+    }
+
+    // the ID of a colour, adding it if it's new
+    pub fn intern(&mut self, name: &str) -> ColourId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.has_rule.push(false);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        id
+    }
+
+    // add the rule "<container> bags contain <contents>"; each colour may
+    // only have one rule
+    pub fn add_rule(&mut self, container: &str, contents: &[(usize, &str)]) -> Result<()> {
+        let c = self.intern(container);
+        if self.has_rule[c] {
+            return Err(anyhow!("second rule for {} bags", container));
+        }
+        self.has_rule[c] = true;
+        for (quantity, name) in contents {
+            let id = self.intern(name);
+            self.contents[c].push((*quantity, id));
+            self.containers[id].push((*quantity, c));
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn id(&self, name: &str) -> Result<ColourId> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("no {} bags in the rules", name))
+    }
+
+    pub fn name(&self, id: ColourId) -> &str {
+        &self.names[id]
+    }
+
+    pub fn contents(&self, id: ColourId) -> &[(usize, ColourId)] {
+        &self.contents[id]
+    }
+
+    // "a -> b -> a"
+    pub fn describe_cycle(&self, cycle: &[ColourId]) -> String {
+        let names: Vec<&str> = cycle
+            .iter()
+            .chain(cycle.first())
+            .map(|id| self.name(*id))
+            .collect();
+        names.join(" -> ")
+    }
+
+    pub fn cycle_error(&self, cycle: &[ColourId]) -> anyhow::Error {
+        anyhow!("bags contain themselves: {}", self.describe_cycle(cycle))
+    }
+
+    // Depth-first search of the contents of the given roots, returning every
+    // colour reached in postorder (contents before their containers), or the
+    // first cycle found: colours each directly containing the next, the last
+    // containing the first.
+    pub fn postorder(&self, roots: &[ColourId]) -> Result<Vec<ColourId>, Vec<ColourId>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            OnPath,
+            Done,
+        }
+        let mut state = vec![State::New; self.len()];
+        let mut order = Vec::new();
+        for &root in roots {
+            if state[root] != State::New {
+                continue;
+            }
+            // the current path, with the index of the next child to visit
+            let mut path = vec![(root, 0)];
+            state[root] = State::OnPath;
+            while let Some((id, next)) = path.pop() {
+                match self.contents[id].get(next) {
+                    Some(&(_, child)) => {
+                        path.push((id, next + 1));
+                        match state[child] {
+                            State::New => {
+                                state[child] = State::OnPath;
+                                path.push((child, 0));
+                            }
+                            State::OnPath => {
+                                let start = path.iter().position(|(c, _)| *c == child).unwrap();
+                                return Err(path[start..].iter().map(|(c, _)| *c).collect());
+                            }
+                            State::Done => (),
+                        }
+                    }
+                    None => {
+                        state[id] = State::Done;
+                        order.push(id);
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    // a cycle of colours which (eventually) contain themselves, if there is one
+    pub fn find_cycle(&self) -> Option<Vec<ColourId>> {
+        let all: Vec<ColourId> = (0..self.len()).collect();
+        self.postorder(&all).err()
+    }
+
+    // every colour, each before any colour it contains
+    pub fn topological_order(&self) -> Result<Vec<ColourId>> {
+        let all: Vec<ColourId> = (0..self.len()).collect();
+        match self.postorder(&all) {
+            Ok(mut order) => {
+                order.reverse();
+                Ok(order)
+            }
+            Err(cycle) => Err(self.cycle_error(&cycle)),
+        }
+    }
+
+    // for every colour, whether it can be reached from the given colour along
+    // the given edges (not counting the colour itself, unless on a cycle)
+    fn reachable(&self, id: ColourId, edges: &[Vec<(usize, ColourId)>]) -> Vec<bool> {
+        let mut seen = vec![false; self.len()];
+        let mut stack = vec![id];
+        while let Some(c) = stack.pop() {
+            for &(_, next) in &edges[c] {
+                if !seen[next] {
+                    seen[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    // for every colour, whether it can eventually contain the given colour
+    pub fn can_contain(&self, id: ColourId) -> Vec<bool> {
+        self.reachable(id, &self.containers)
+    }
+
+    // for every colour, whether the given colour eventually contains it
+    pub fn inside(&self, id: ColourId) -> Vec<bool> {
+        self.reachable(id, &self.contents)
+    }
+
+    // how many bags the given colour must contain, counting each of its
+    // contents once per level of nesting
+    pub fn count_contents(&self, id: ColourId) -> Result<u64> {
+        Ok(self.count_all_contents(id)?[id])
+    }
+
+    // count_contents() for the given colour and every colour below it (0 for
+    // the rest), memoised
+    pub fn count_all_contents(&self, id: ColourId) -> Result<Vec<u64>> {
+        let order = self
+            .postorder(&[id])
+            .map_err(|cycle| self.cycle_error(&cycle))?;
+        let mut totals: Vec<u64> = vec![0; self.len()];
+        for c in order {
+            totals[c] = self.contents[c]
+                .iter()
+                .try_fold(0u64, |sum, &(quantity, child)| {
+                    totals[child]
+                        .checked_add(1)
+                        .and_then(|n| n.checked_mul(quantity as u64))
+                        .and_then(|n| sum.checked_add(n))
+                })
+                .ok_or_else(|| anyhow!("{} bags hold too many bags to count", self.name(c)))?;
+        }
+        Ok(totals)
+    }
+}
@@ -0,0 +1,142 @@
+// ==== day 7: bag queries
+//
+// Questions about any colour, not just shiny gold. Each one is answered with a
+// single pass over the colours above or below the query, so they stay fast on
+// rule sets with many thousands of colours.
+
+use super::graph::{BagGraph, ColourId};
+use anyhow::{anyhow, Result};
+
+// One way of nesting bags: the outer colour, then each colour inside the one
+// before with how many of it that one holds.
+pub struct Path {
+    pub from: ColourId,
+    pub steps: Vec<(usize, ColourId)>,
+}
+
+impl Path {
+    // how many of the innermost bag this path puts in the outermost
+    pub fn multiplicity(&self) -> u64 {
+        self.steps
+            .iter()
+            .fold(1, |m: u64, (q, _)| m.saturating_mul(*q as u64))
+    }
+}
+
+// every path from one colour down to another, with totals over all paths
+pub struct Paths {
+    pub paths: Vec<Path>, // up to the requested limit
+    pub count: u64,       // number of paths, which may be more than listed
+    pub total: u64,       // how many of the inner colour the outer one holds
+}
+
+impl BagGraph {
+    // every colour which can eventually contain the given colour, by name
+    pub fn all_containers(&self, id: ColourId) -> Vec<ColourId> {
+        let mut found: Vec<ColourId> = self
+            .can_contain(id)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(c, yes)| if yes { Some(c) } else { None })
+            .collect();
+        found.sort_by_key(|c| self.name(*c));
+        found
+    }
+
+    // The containment paths from one colour down to another, listing at most
+    // `limit` of them. Counting is memoised over the colours below `from`, and
+    // listing only follows colours which lead to `to`, so the work is linear
+    // in the size of the graph plus the size of the listing.
+    pub fn paths(&self, from: ColourId, to: ColourId, limit: usize) -> Result<Paths> {
+        let order = self
+            .postorder(&[from])
+            .map_err(|cycle| self.cycle_error(&cycle))?;
+
+        // paths and bags from each colour down to `to`, or None if it can't
+        // get there
+        let mut below: Vec<Option<(u64, u64)>> = vec![None; self.len()];
+        below[to] = Some((1, 1));
+        for &c in order.iter().filter(|c| **c != to) {
+            for &(q, child) in self.contents(c) {
+                if let Some((n, bags)) = below[child] {
+                    let (sum_n, sum_bags) = below[c].unwrap_or((0, 0));
+                    let sum_bags = (q as u64)
+                        .checked_mul(bags)
+                        .and_then(|bags| sum_bags.checked_add(bags))
+                        .ok_or_else(|| anyhow!("too many bags to count"))?;
+                    below[c] = Some((sum_n.saturating_add(n), sum_bags));
+                }
+            }
+        }
+        let (count, total) = match (from == to, below[from]) {
+            (false, Some(totals)) => totals,
+            _ => (0, 0),
+        };
+
+        // list paths depth first, in rule order
+        let mut paths = Vec::new();
+        let mut path: Vec<(usize, ColourId)> = Vec::new();
+        let mut stack: Vec<(ColourId, usize)> = vec![(from, 0)]; // colour, next child
+        while let Some((c, next)) = stack.pop() {
+            if paths.len() >= limit || from == to {
+                break;
+            }
+            match self.contents(c).get(next) {
+                Some(&(q, child)) => {
+                    stack.push((c, next + 1));
+                    if child == to {
+                        let mut steps = path.clone();
+                        steps.push((q, child));
+                        paths.push(Path { from, steps });
+                    } else if below[child].is_some() {
+                        path.push((q, child));
+                        stack.push((child, 0));
+                    }
+                }
+                None => {
+                    path.pop();
+                }
+            }
+        }
+
+        Ok(Paths {
+            paths,
+            count,
+            total,
+        })
+    }
+
+    // "shiny gold -> 1 dark olive -> 3 faded blue"
+    pub fn describe_path(&self, path: &Path) -> String {
+        let mut s = self.name(path.from).to_string();
+        for (q, c) in &path.steps {
+            s += &format!(" -> {} {}", q, self.name(*c));
+        }
+        s
+    }
+
+    // how many levels of bags nest inside the given colour (0 if it holds
+    // nothing), or inside any colour at all
+    pub fn depth(&self, id: Option<ColourId>) -> Result<usize> {
+        let roots: Vec<ColourId> = match id {
+            Some(id) => vec![id],
+            None => (0..self.len()).collect(),
+        };
+        let order = self
+            .postorder(&roots)
+            .map_err(|cycle| self.cycle_error(&cycle))?;
+        let mut depth = vec![0; self.len()];
+        for &c in &order {
+            depth[c] = self
+                .contents(c)
+                .iter()
+                .map(|(_, child)| depth[*child] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        Ok(match id {
+            Some(id) => depth[id],
+            None => depth.into_iter().max().unwrap_or(0),
+        })
+    }
+}
@@ -2,13 +2,33 @@
 
 use crate::utils;
 use anyhow::{anyhow, Result};
-use std::str::FromStr;
+use isa::{Operand, Reg};
+use std::{convert::TryFrom, fmt, fs, io, str::FromStr};
 
-#[derive(Clone, Copy, Debug)]
+mod analysis;
+mod asm;
+mod cfg;
+mod debugger;
+mod dot;
+mod isa;
+mod snapshot;
+mod trace;
+
+// see isa.rs for what each instruction does
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Instruction {
     Acc(i32),
     Jmp(i32),
     Nop(i32),
+    Add(Operand),
+    Sub(Operand),
+    Mul(Operand),
+    Div(Operand),
+    Ld(Operand),
+    St(Reg),
+    Jz(i32),
+    Jnz(i32),
+    Out(Operand),
 }
 
 impl FromStr for Instruction {
@@ -19,94 +39,265 @@ impl FromStr for Instruction {
         if parsed.len() != 2 {
             return Err(anyhow!("instruction parse error"));
         }
-        let opcode = parsed[0];
-        let operand = parsed[1].parse::<i32>()?;
+        Instruction::new(parsed[0], parsed[1].parse()?)
+    }
+}
+
+impl Instruction {
+    // exchange jmp <=> nop, or None for instructions which can't be flipped
+    fn flipped(self) -> Option<Self> {
+        match self {
+            Instruction::Jmp(n) => Some(Instruction::Nop(n)),
+            Instruction::Nop(n) => Some(Instruction::Jmp(n)),
+            _ => None,
+        }
+    }
 
-        match opcode {
-            "acc" => Ok(Instruction::Acc(operand)),
-            "jmp" => Ok(Instruction::Jmp(operand)),
-            "nop" => Ok(Instruction::Nop(operand)),
-            _ => Err(anyhow!("unknown instruction")),
+    fn new(opcode: &str, operand: Operand) -> Result<Self> {
+        let def = isa::lookup(opcode)?;
+        isa::check_operand(def, operand)?;
+        Ok(match (def.name, operand) {
+            ("acc", Operand::Imm(n)) => Instruction::Acc(n),
+            ("jmp", Operand::Imm(n)) => Instruction::Jmp(n),
+            ("nop", Operand::Imm(n)) => Instruction::Nop(n),
+            ("add", x) => Instruction::Add(x),
+            ("sub", x) => Instruction::Sub(x),
+            ("mul", x) => Instruction::Mul(x),
+            ("div", x) => Instruction::Div(x),
+            ("ld", x) => Instruction::Ld(x),
+            ("st", Operand::Reg(r)) => Instruction::St(r),
+            ("jz", Operand::Imm(n)) => Instruction::Jz(n),
+            ("jnz", Operand::Imm(n)) => Instruction::Jnz(n),
+            ("out", x) => Instruction::Out(x),
+            _ => unreachable!("operand already checked against the instruction set"),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Instruction::Acc(_) => "acc",
+            Instruction::Jmp(_) => "jmp",
+            Instruction::Nop(_) => "nop",
+            Instruction::Add(_) => "add",
+            Instruction::Sub(_) => "sub",
+            Instruction::Mul(_) => "mul",
+            Instruction::Div(_) => "div",
+            Instruction::Ld(_) => "ld",
+            Instruction::St(_) => "st",
+            Instruction::Jz(_) => "jz",
+            Instruction::Jnz(_) => "jnz",
+            Instruction::Out(_) => "out",
+        }
+    }
+
+    fn operand(&self) -> Operand {
+        match *self {
+            Instruction::Acc(n)
+            | Instruction::Jmp(n)
+            | Instruction::Nop(n)
+            | Instruction::Jz(n)
+            | Instruction::Jnz(n) => Operand::Imm(n),
+            Instruction::Add(x)
+            | Instruction::Sub(x)
+            | Instruction::Mul(x)
+            | Instruction::Div(x)
+            | Instruction::Ld(x)
+            | Instruction::Out(x) => x,
+            Instruction::St(r) => Operand::Reg(r),
+        }
+    }
+
+    fn def(&self) -> &'static isa::OpcodeDef {
+        isa::lookup(self.name()).expect("instruction missing from the instruction set")
+    }
+
+    // the relative offset of a (possibly conditional) jump
+    fn jump_offset(&self) -> Option<i32> {
+        match *self {
+            Instruction::Jmp(n) | Instruction::Jz(n) | Instruction::Jnz(n) => Some(n),
+            _ => None,
         }
     }
 }
 
+// writes the puzzle format, e.g. "jmp -4" or "nop +0"
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{} {}", self.name(), self.operand()))
+    }
+}
+
+// what stops a program which never terminates
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Guard {
+    #[default]
+    RepeatedInstruction, // halt before any instruction runs a second time (the puzzle's rule)
+    StepLimit(usize), // halt after this many steps, allowing loops
+}
+
 #[derive(Debug, Default)]
 struct Console {
-    pc: usize,                 // program counter
-    hits: Vec<usize>,          // instruction hits
-    accumulator: i32,          // accumulator (starts at 0)
-    program: Vec<Instruction>, // program is just an array of instructions
+    pc: usize,                              // program counter
+    hits: Vec<usize>,                       // instruction hits
+    accumulator: i32,                       // accumulator (starts at 0)
+    registers: [i32; isa::REGISTERS.len()], // general purpose registers (start at 0)
+    output: Vec<i32>,                       // values written by "out"
+    steps: usize,                           // instructions executed
+    trap: Option<Trap>,                     // set when an instruction faults
+    guard: Guard,                           // infinite loop protection
+    program: Vec<Instruction>,              // program is just an array of instructions
+    trace: Option<Vec<trace::TraceEntry>>,  // executed instructions, if recording
 }
 
 impl FromStr for Console {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let memory: Vec<Instruction> = s
-            .lines()
-            .map(|l| Instruction::from_str(l).unwrap())
-            .collect();
-        Ok(Console {
-            pc: 0,
-            hits: vec![0; memory.len()],
-            accumulator: 0,
-            program: memory,
-        })
+        Ok(Console::from_program(asm::assemble(s)?))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Trap {
+    Overflow,
+    DivideByZero,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::Overflow => write!(f, "accumulator overflow"),
+            Trap::DivideByZero => write!(f, "division by zero"),
+        }
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Status {
     Running,
     Completed,
     Halted,
+    Trapped(Trap),
 }
 
 impl Console {
+    fn from_program(program: Vec<Instruction>) -> Self {
+        Console {
+            hits: vec![0; program.len()],
+            program,
+            ..Console::default()
+        }
+    }
+
+    // start recording a trace of every executed instruction (cleared on reset)
+    fn record_trace(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+
     fn reset(&mut self) {
         self.pc = 0;
         self.accumulator = 0;
+        self.registers = Default::default();
+        self.output.clear();
+        self.steps = 0;
+        self.trap = None;
         self.hits = vec![0; self.program.len()];
+        if let Some(trace) = &mut self.trace {
+            trace.clear();
+        }
     }
 
     fn get_run_state(&self) -> Status {
+        // check for faults
+        if let Some(trap) = self.trap {
+            Status::Trapped(trap)
+        }
         // check pc
-        if !(0..self.program.len()).contains(&self.pc) {
+        else if !(0..self.program.len()).contains(&self.pc) {
             Status::Completed
         }
         // guard against infinite loops
-        else if self.hits[self.pc] > 0 {
+        else if match self.guard {
+            Guard::RepeatedInstruction => self.hits[self.pc] > 0,
+            Guard::StepLimit(n) => self.steps >= n,
+        } {
             Status::Halted
         } else {
             Status::Running
         }
     }
 
+    fn value(&self, operand: Operand) -> i32 {
+        match operand {
+            Operand::Imm(n) => n,
+            Operand::Reg(r) => self.registers[r.0],
+        }
+    }
+
     fn step(&mut self) -> Status {
         // verify we're running
         if self.get_run_state() != Status::Running {
             return self.get_run_state();
         }
 
-        // execute instruction at pc
-        self.hits[self.pc] += 1;
-        match self.program.get(self.pc).unwrap() {
-            Instruction::Acc(n) => {
-                self.accumulator += n;
-                self.pc += 1;
+        // execute instruction at pc, computing the new accumulator (or a
+        // fault) and next pc
+        let (pc, acc) = (self.pc, self.accumulator);
+        let instruction = self.program[pc];
+        let mut next_pc = pc as i64 + 1;
+        let result = match instruction {
+            Instruction::Acc(n) => acc.checked_add(n).ok_or(Trap::Overflow),
+            Instruction::Add(x) => acc.checked_add(self.value(x)).ok_or(Trap::Overflow),
+            Instruction::Sub(x) => acc.checked_sub(self.value(x)).ok_or(Trap::Overflow),
+            Instruction::Mul(x) => acc.checked_mul(self.value(x)).ok_or(Trap::Overflow),
+            Instruction::Div(x) => match self.value(x) {
+                0 => Err(Trap::DivideByZero),
+                d => acc.checked_div(d).ok_or(Trap::Overflow),
+            },
+            Instruction::Ld(x) => Ok(self.value(x)),
+            Instruction::St(r) => {
+                self.registers[r.0] = acc;
+                Ok(acc)
+            }
+            Instruction::Out(x) => {
+                self.output.push(self.value(x));
+                Ok(acc)
             }
             Instruction::Jmp(n) => {
-                if *n < 0 {
-                    self.pc -= n.abs() as usize;
-                } else {
-                    self.pc += *n as usize;
+                next_pc = pc as i64 + i64::from(n);
+                Ok(acc)
+            }
+            Instruction::Jz(n) | Instruction::Jnz(n) => {
+                if (acc == 0) == matches!(instruction, Instruction::Jz(_)) {
+                    next_pc = pc as i64 + i64::from(n);
                 }
+                Ok(acc)
             }
-            Instruction::Nop(_) => {
-                self.pc += 1;
+            Instruction::Nop(_) => Ok(acc),
+        };
+        match result {
+            Ok(acc) => self.accumulator = acc,
+            Err(trap) => {
+                // leave pc on the faulting instruction
+                self.trap = Some(trap);
+                return Status::Trapped(trap);
             }
         }
+        self.hits[pc] += 1;
+        self.steps += 1;
+
+        // jumping before the start leaves the program, like jumping past the end
+        self.pc = usize::try_from(next_pc).unwrap_or(usize::MAX);
+
+        if let Some(trace) = &mut self.trace {
+            trace.push(trace::TraceEntry {
+                step: trace.len(),
+                pc,
+                instruction,
+                acc_before: acc,
+                acc_after: self.accumulator,
+            });
+        }
         Status::Running
     }
 
@@ -117,49 +308,40 @@ impl Console {
     }
 
     fn repair(&mut self) -> Result<i32> {
-        // first run() so we can find every instruction executed
-        // since we know we only have to flip one instruction (jmp <=> nop), then
-        // we're guaranteed to already hit it on this first run
-        self.run();
-
-        // now that we've run once, find the candidate instructions
-        let candidate_indices: Vec<usize> = self
-            .hits
-            .iter()
-            .enumerate()
-            .filter_map(|(i, hits)| if *hits > 0 { Some(i) } else { None })
-            .filter(|i| match self.program.get(*i as usize).unwrap() {
-                // filter instructions we can modify
-                Instruction::Jmp(_) => true,
-                Instruction::Nop(n) => *n != 0,
-                Instruction::Acc(_) => false,
-            })
-            .collect();
-
-        // now test each of these candidates by exchanging jmp <=> nop
-        for i in candidate_indices {
-            // save original instruction
-            let original_instruction = self.program[i];
-
-            // exchange jmp<=>nop
-            self.program[i] = match &self.program[i] {
-                Instruction::Jmp(n) => Instruction::Nop(*n),
-                Instruction::Nop(n) => Instruction::Jmp(*n),
-                _ => unreachable!("invalid candidate instruction"),
+        // since we know we only have to flip one instruction (jmp <=> nop), it
+        // must be one the original program executes; walk the original run and,
+        // at each candidate, fork a run of the flipped program from that point
+        self.reset();
+        while self.get_run_state() == Status::Running {
+            let candidate = match self.program[self.pc] {
+                // flipping "nop +0" can only give an infinite loop
+                Instruction::Nop(0) => None,
+                instruction => instruction.flipped(),
             };
+            if let Some(flipped) = candidate {
+                let fork = self.snapshot();
+                self.program[self.pc] = flipped;
+                while self.step() == Status::Running {}
+                if self.get_run_state() == Status::Completed {
+                    return Ok(self.accumulator);
+                }
 
-            // run program
-            self.run();
-            if self.get_run_state() == Status::Completed {
-                return Ok(self.accumulator);
+                // that didn't work, go back to the original program
+                self.restore(&fork);
             }
-
-            // that didn't work, restore the original instruction
-            self.program[i] = original_instruction;
+            self.step();
         }
 
         Err(anyhow!("no result found"))
     }
+
+    // repair by static analysis of the control flow graph instead of search,
+    // leaving the flip in place
+    fn repair_static(&mut self) -> Result<i32> {
+        let i = cfg::find_repair(&self.program)?;
+        self.program[i] = self.program[i].flipped().unwrap();
+        Ok(self.run())
+    }
 }
 
 pub fn run() -> Result<(i32, i32)> {
@@ -169,9 +351,158 @@ pub fn run() -> Result<(i32, i32)> {
     let mut console = Console::from_str(&data)?;
 
     let p1 = console.run();
-    let p2 = console.repair()?;
+    let p2 = console.repair_static()?;
 
     assert_eq!(p1, answers[0] as i32);
     assert_eq!(p2, answers[1] as i32);
     Ok((p1, p2))
 }
+
+// how a run ended, with the final machine state
+fn run_summary(console: &Console) -> String {
+    let status = match console.get_run_state() {
+        Status::Trapped(trap) => format!("trapped ({}) at pc {}", trap, console.pc),
+        Status::Halted => format!("halted after {} steps", console.steps),
+        Status::Running => "running".to_string(),
+        Status::Completed => "completed".to_string(),
+    };
+    let registers: Vec<String> = isa::REGISTERS
+        .iter()
+        .zip(console.registers.iter())
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    format!(
+        "{}: acc={} {} steps={}",
+        status,
+        console.accumulator,
+        registers.join(" "),
+        console.steps
+    )
+}
+
+// command line tools for console programs
+pub fn cli(args: &[String]) -> Result<()> {
+    match args {
+        [cmd, filename] if cmd == "asm" => {
+            // assemble labels and comments down to the puzzle format
+            asm::assemble(&utils::read_file(filename)?)?
+                .iter()
+                .for_each(|i| println!("{}", i));
+            Ok(())
+        }
+        [cmd, filename] if cmd == "disasm" => {
+            print!(
+                "{}",
+                asm::disassemble(&asm::assemble(&utils::read_file(filename)?)?)
+            );
+            Ok(())
+        }
+        [cmd, filename] if cmd == "debug" => {
+            let console = Console::from_str(&utils::read_file(filename)?)?;
+            let stdin = io::stdin();
+            debugger::Debugger::new(console).repl(stdin.lock(), io::stdout())
+        }
+        [cmd, filename, rest @ ..] if (cmd == "exec" || cmd == "resume") && rest.len() <= 2 => {
+            // run a program which may loop, up to a step limit, from the start
+            // or from a saved snapshot, optionally saving a snapshot at the end
+            let mut console = if cmd == "exec" {
+                Console::from_str(&utils::read_file(filename)?)?
+            } else {
+                let mut console = Console::default();
+                console.restore(&snapshot::load(filename)?);
+                console
+            };
+            let limit = match rest.first() {
+                Some(n) => n.parse()?,
+                None => 1_000_000,
+            };
+            console.guard = Guard::StepLimit(console.steps + limit);
+            while console.step() == Status::Running {}
+            console.output.iter().for_each(|x| println!("{}", x));
+            eprintln!("{}", run_summary(&console));
+            if let Some(out) = rest.get(1) {
+                snapshot::save(&console.snapshot(), out)?;
+                eprintln!("snapshot written to {}", out);
+            }
+            Ok(())
+        }
+        [cmd, filename, rest @ ..] if cmd == "dot" && rest.len() <= 1 => {
+            // run the program, then draw its control flow graph
+            let mut console = Console::from_str(&utils::read_file(filename)?)?;
+            console.run();
+            let graph = dot::to_dot(&console);
+            match rest.first() {
+                Some(out) => fs::write(out, graph)?,
+                None => print!("{}", graph),
+            }
+            Ok(())
+        }
+        [cmd, filename] if cmd == "analyse" => {
+            let program = asm::assemble(&utils::read_file(filename)?)?;
+            print!("{}", analysis::analyse(&program));
+            Ok(())
+        }
+        [cmd, filename] if cmd == "repair" => {
+            // report the flip(s) which make the program terminate
+            let program = asm::assemble(&utils::read_file(filename)?)?;
+            let candidates = cfg::repair_candidates(&program)?;
+            match cfg::find_repair(&program) {
+                Ok(i) => println!("flip instruction {}: {}", i, program[i]),
+                Err(e) => println!("{}", e),
+            }
+            for i in candidates {
+                println!("{:>4}: {} => {}", i, program[i], program[i].flipped().unwrap());
+            }
+            Ok(())
+        }
+        [cmd, filename, out, rest @ ..] if cmd == "trace" => {
+            // record a run (of the repaired program, if asked) and save it
+            let mut console = Console::from_str(&utils::read_file(filename)?)?;
+            let original = console.program.clone();
+            console.record_trace();
+            match rest {
+                [] => {
+                    console.run();
+                }
+                [flag] if flag == "repaired" => {
+                    console.repair()?;
+                }
+                _ => return Err(anyhow!("usage: day08 trace FILE OUT [repaired]")),
+            }
+            let trace = trace::Trace::new(
+                &original,
+                &console.program,
+                console.trace.unwrap_or_default(),
+            );
+            trace::save(&trace, out)?;
+            for (i, instruction) in &trace.patches {
+                println!("patched {}: {}", i, instruction);
+            }
+            println!("{} steps written to {}", trace.entries.len(), out);
+            Ok(())
+        }
+        [cmd, filename, trace_file, step] if cmd == "replay" => {
+            // rebuild the console state at a step of a recorded trace
+            let program = asm::assemble(&utils::read_file(filename)?)?;
+            let trace = trace::load(trace_file)?;
+            let console = trace::replay(&program, &trace, step.parse()?)?;
+            println!(
+                "after step {}: pc={} acc={} executed={}",
+                step,
+                console.pc,
+                console.accumulator,
+                console.hits.iter().filter(|h| **h > 0).count()
+            );
+            if let Some(i) = console.program.get(console.pc) {
+                println!("next: {}: {}", console.pc, i);
+            }
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "usage: day08 asm FILE | day08 disasm FILE | day08 debug FILE | day08 repair FILE\n       \
+             day08 analyse FILE | day08 dot FILE [OUT]\n       \
+             day08 exec FILE [STEPS [SAVE]] | day08 resume SNAPSHOT [STEPS [SAVE]]\n       \
+             day08 trace FILE OUT [repaired] | day08 replay FILE TRACE STEP"
+        )),
+    }
+}
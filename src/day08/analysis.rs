@@ -0,0 +1,149 @@
+// ==== day 8: loop analysis
+//
+// A report on how a program runs, worked out from its control flow graph
+// rather than by running it: the infinite loop it gets stuck in (if any),
+// instructions which can never run, and instructions which every terminating
+// run must pass through.
+
+use super::{cfg, Instruction};
+use std::fmt;
+
+// The loop a puzzle program gets stuck in. Its control flow doesn't depend on
+// the accumulator, so once an instruction repeats the body repeats forever,
+// changing the accumulator by the same amount each time round.
+pub struct Cycle {
+    pub entry: usize,      // first instruction to run a second time
+    pub steps: usize,      // steps executed before first reaching the entry
+    pub acc_on_entry: i64, // accumulator on first reaching the entry
+    pub body: Vec<usize>,  // instructions of one iteration, from the entry
+    pub acc_delta: i64,    // accumulator change per iteration
+}
+
+// how a puzzle program's single execution path ends
+pub enum Outcome {
+    Terminates { steps: usize, acc: i64 },
+    Loops(Cycle),
+}
+
+pub struct Report {
+    pub program: Vec<Instruction>,
+    pub outcome: Result<Outcome, String>, // why not, for extended programs
+    pub unreachable: Vec<usize>,          // never reachable from pc 0
+    pub on_every_path: Option<Vec<usize>>, // None if no path terminates
+}
+
+fn acc_change(instruction: &Instruction) -> i64 {
+    match instruction {
+        Instruction::Acc(n) => i64::from(*n),
+        _ => 0,
+    }
+}
+
+// follow the single execution path of a puzzle program
+fn outcome(program: &[Instruction], cfg: &cfg::Cfg) -> Outcome {
+    let path = cfg.execution_path();
+    let acc: Vec<i64> = path
+        .iter()
+        .scan(0, |acc, i| {
+            let before = *acc;
+            *acc += acc_change(&program[*i]);
+            Some(before)
+        })
+        .collect();
+    let total: i64 = path.iter().map(|i| acc_change(&program[*i])).sum();
+    match path.last().map(|last| cfg.successors(*last)[0]) {
+        Some(next) if next != cfg.exit() => {
+            let start = path.iter().position(|i| *i == next).unwrap();
+            Outcome::Loops(Cycle {
+                entry: next,
+                steps: start,
+                acc_on_entry: acc[start],
+                body: path[start..].to_vec(),
+                acc_delta: total - acc[start],
+            })
+        }
+        _ => Outcome::Terminates {
+            steps: path.len(),
+            acc: total,
+        },
+    }
+}
+
+pub fn analyse(program: &[Instruction]) -> Report {
+    let cfg = cfg::Cfg::new(program);
+    let reachable = cfg.reachable();
+    Report {
+        program: program.to_vec(),
+        outcome: match cfg::check_puzzle_program(program) {
+            Ok(()) => Ok(outcome(program, &cfg)),
+            Err(e) => Err(e.to_string()),
+        },
+        unreachable: (0..program.len()).filter(|i| !reachable[*i]).collect(),
+        on_every_path: cfg.exit_dominators(),
+    }
+}
+
+// a sorted list of instructions as ranges, e.g. "3-5, 9"
+fn ranges(list: &[usize]) -> String {
+    if list.is_empty() {
+        return "none".to_string();
+    }
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for &i in list {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == i => *end = i,
+            _ => runs.push((i, i)),
+        }
+    }
+    let runs: Vec<String> = runs
+        .iter()
+        .map(|(start, end)| match end - start {
+            0 => format!("{}", start),
+            _ => format!("{}-{}", start, end),
+        })
+        .collect();
+    runs.join(", ")
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Err(e) => writeln!(f, "loop: not analysed ({})", e)?,
+            Ok(Outcome::Terminates { steps, acc }) => {
+                writeln!(f, "terminates after {} steps with acc {}", steps, acc)?
+            }
+            Ok(Outcome::Loops(cycle)) => {
+                writeln!(
+                    f,
+                    "infinite loop: entered at instruction {} after {} steps with acc {}",
+                    cycle.entry, cycle.steps, cycle.acc_on_entry
+                )?;
+                writeln!(
+                    f,
+                    "  {} instructions per iteration, acc {:+} per iteration:",
+                    cycle.body.len(),
+                    cycle.acc_delta
+                )?;
+                for i in &cycle.body {
+                    writeln!(f, "  {:>6}: {}", i, self.program[*i])?;
+                }
+            }
+        }
+        writeln!(
+            f,
+            "unreachable instructions ({}): {}",
+            self.unreachable.len(),
+            ranges(&self.unreachable)
+        )?;
+        match &self.on_every_path {
+            None => writeln!(f, "no path from pc 0 terminates")?,
+            Some(list) => writeln!(
+                f,
+                "on every path to termination ({}): {}",
+                list.len(),
+                ranges(list)
+            )?,
+        }
+        Ok(())
+    }
+}
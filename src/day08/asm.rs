@@ -0,0 +1,97 @@
+// ==== day 8: assembler and disassembler
+
+use super::{
+    isa::{self, Operand, OperandKind},
+    Instruction,
+};
+use crate::utils::ParseError;
+use std::collections::HashMap;
+
+// true for names like "loop_start": a letter or '_', then letters, digits or '_'
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Assemble source text into a program.
+//
+// The source is the puzzle format ("jmp -4"), extended with:
+//   - comments, from '#' or ';' to the end of the line
+//   - labels, "name:" at the start of a line, naming the next instruction
+//   - label operands, "jmp name", resolved to the relative offset of the label
+//     (for any jump in the instruction set)
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, ParseError> {
+    // first pass: strip comments, and gather labels and statements
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut statements: Vec<(usize, &str, &str)> = Vec::new(); // (line, opcode, operand)
+    for (i, line) in source.lines().enumerate() {
+        let mut text = line.split(['#', ';']).next().unwrap().trim();
+
+        if let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_label(label) {
+                return Err(ParseError::new(i + 1, format!("bad label '{}'", label)));
+            }
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(ParseError::new(
+                    i + 1,
+                    format!("duplicate label '{}'", label),
+                ));
+            }
+            text = text[colon + 1..].trim();
+        }
+
+        let parsed: Vec<&str> = text.split_whitespace().collect();
+        match parsed.len() {
+            0 => (),
+            2 => statements.push((i + 1, parsed[0], parsed[1])),
+            _ => return Err(ParseError::new(i + 1, format!("bad statement '{}'", text))),
+        }
+    }
+
+    // second pass: resolve label operands into relative offsets
+    statements
+        .iter()
+        .enumerate()
+        .map(|(index, (line, opcode, operand))| {
+            let error = |e| ParseError::new(*line, e);
+            let def = isa::lookup(opcode).map_err(error)?;
+            let operand = match (def.operand, operand.parse::<i32>(), labels.get(operand)) {
+                (_, Ok(n), _) => Operand::Imm(n),
+                (OperandKind::Offset, Err(_), Some(target)) => {
+                    Operand::Imm(*target as i32 - index as i32)
+                }
+                (OperandKind::Offset, Err(_), None) => {
+                    return Err(ParseError::new(
+                        *line,
+                        format!("unknown label '{}'", operand),
+                    ))
+                }
+                _ => operand.parse().map_err(error)?,
+            };
+            Instruction::new(opcode, operand).map_err(error)
+        })
+        .collect()
+}
+
+// Disassemble a program into the puzzle format, annotating each instruction
+// with its index and each jump with its target. The annotations are comments,
+// so assembling the output gives back the same program.
+pub fn disassemble(program: &[Instruction]) -> String {
+    program
+        .iter()
+        .enumerate()
+        .map(|(i, instruction)| match instruction.jump_offset() {
+            Some(n) => format!(
+                "{:<12}; {:>4} -> {}\n",
+                instruction,
+                i,
+                i as i64 + i64::from(n)
+            ),
+            None => format!("{:<12}; {:>4}\n", instruction, i),
+        })
+        .collect()
+}
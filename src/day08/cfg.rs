@@ -0,0 +1,262 @@
+// ==== day 8: control flow graph
+//
+// Nodes are the instructions 0..n plus an exit node n, which stands for every
+// pc outside the program. Puzzle programs give every instruction exactly one
+// successor, which makes the repair analysis linear: one reverse search from
+// the exit finds every instruction which already terminates, and one walk of
+// the original execution path checks each flip against that set.
+
+use super::{isa::InstructionSet, Instruction};
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+
+pub struct Cfg {
+    successors: Vec<Vec<usize>>, // successors of each instruction, n for the exit
+}
+
+// the nodes which can follow instruction i, clamping any pc outside the
+// program to the exit node; conditional jumps have two
+fn successors(i: usize, instruction: &Instruction, n: usize) -> Vec<usize> {
+    let clamp = |target: i64| {
+        if 0 <= target && target < n as i64 {
+            target as usize
+        } else {
+            n
+        }
+    };
+    let next = clamp(i as i64 + 1);
+    match (instruction, instruction.jump_offset()) {
+        (Instruction::Jmp(_), Some(offset)) => vec![clamp(i as i64 + i64::from(offset))],
+        (_, Some(offset)) => vec![next, clamp(i as i64 + i64::from(offset))],
+        (_, None) => vec![next],
+    }
+}
+
+impl Cfg {
+    pub fn new(program: &[Instruction]) -> Self {
+        let n = program.len();
+        Cfg {
+            successors: program
+                .iter()
+                .enumerate()
+                .map(|(i, instruction)| successors(i, instruction, n))
+                .collect(),
+        }
+    }
+
+    pub fn successors(&self, node: usize) -> &[usize] {
+        &self.successors[node]
+    }
+
+    pub fn exit(&self) -> usize {
+        self.successors.len()
+    }
+
+    // for every node (including the exit), whether some path from it reaches
+    // the exit; found by searching backwards from the exit
+    pub fn reaches_exit(&self) -> Vec<bool> {
+        let exit = self.exit();
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); exit + 1];
+        for (i, succ) in self.successors.iter().enumerate() {
+            for s in succ {
+                predecessors[*s].push(i);
+            }
+        }
+
+        let mut reaches = vec![false; exit + 1];
+        reaches[exit] = true;
+        let mut queue: VecDeque<usize> = VecDeque::from(vec![exit]);
+        while let Some(node) = queue.pop_front() {
+            for p in &predecessors[node] {
+                if !reaches[*p] {
+                    reaches[*p] = true;
+                    queue.push_back(*p);
+                }
+            }
+        }
+        reaches
+    }
+
+    // for every node (including the exit), whether some path from pc 0
+    // reaches it
+    pub fn reachable(&self) -> Vec<bool> {
+        let exit = self.exit();
+        let mut seen = vec![false; exit + 1];
+        let mut stack = vec![0];
+        seen[0] = true;
+        while let Some(node) = stack.pop() {
+            if node == exit {
+                continue;
+            }
+            for s in &self.successors[node] {
+                if !seen[*s] {
+                    seen[*s] = true;
+                    stack.push(*s);
+                }
+            }
+        }
+        seen
+    }
+
+    // The instructions on every path from pc 0 to the exit, in path order, or
+    // None if no path gets there. These are the dominators of the exit node,
+    // found with the iterative algorithm of Cooper, Harvey and Kennedy ("A
+    // Simple, Fast Dominance Algorithm") over a reverse postorder.
+    pub fn exit_dominators(&self) -> Option<Vec<usize>> {
+        let exit = self.exit();
+        let successors = |node: usize| -> &[usize] {
+            if node == exit {
+                &[]
+            } else {
+                &self.successors[node]
+            }
+        };
+
+        // number the reachable nodes in postorder, with an explicit stack of
+        // (node, index of the next successor to visit)
+        let mut order = vec![usize::MAX; exit + 1];
+        let mut postorder = Vec::new();
+        let mut stack = vec![(0, 0)];
+        order[0] = 0; // visited, numbered properly below
+        while let Some((node, next)) = stack.pop() {
+            match successors(node).get(next) {
+                Some(&s) => {
+                    stack.push((node, next + 1));
+                    if order[s] == usize::MAX {
+                        order[s] = 0;
+                        stack.push((s, 0));
+                    }
+                }
+                None => {
+                    order[node] = postorder.len();
+                    postorder.push(node);
+                }
+            }
+        }
+        if order[exit] == usize::MAX {
+            return None;
+        }
+
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); exit + 1];
+        for &node in &postorder {
+            for &s in successors(node) {
+                predecessors[s].push(node);
+            }
+        }
+
+        // walk two dominator tree paths up to their common ancestor
+        let intersect = |idom: &[usize], mut a: usize, mut b: usize| {
+            while a != b {
+                while order[a] < order[b] {
+                    a = idom[a];
+                }
+                while order[b] < order[a] {
+                    b = idom[b];
+                }
+            }
+            a
+        };
+        let mut idom = vec![usize::MAX; exit + 1];
+        idom[0] = 0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in postorder.iter().rev().skip(1) {
+                let mut processed = predecessors[node]
+                    .iter()
+                    .copied()
+                    .filter(|p| idom[*p] != usize::MAX);
+                let first = processed
+                    .next()
+                    .expect("reachable node without predecessor");
+                let new_idom = processed.fold(first, |d, p| intersect(&idom, d, p));
+                if idom[node] != new_idom {
+                    idom[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut dominators = Vec::new();
+        let mut node = exit;
+        while node != 0 {
+            node = idom[node];
+            dominators.push(node);
+        }
+        dominators.reverse();
+        Some(dominators)
+    }
+
+    // the instructions executed from pc 0, in order, up to the exit or the
+    // first repeated instruction; only meaningful when there are no
+    // conditional jumps (it follows the first successor)
+    pub fn execution_path(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.exit()];
+        let mut path = Vec::new();
+        let mut node = 0;
+        while node < self.exit() && !seen[node] {
+            seen[node] = true;
+            path.push(node);
+            node = self.successors[node][0];
+        }
+        path
+    }
+}
+
+// The static analysis is exact only for the puzzle's instruction set: with
+// conditional jumps, whether a path is taken depends on the accumulator.
+pub fn check_puzzle_program(program: &[Instruction]) -> Result<()> {
+    match program
+        .iter()
+        .position(|i| i.def().set != InstructionSet::Puzzle)
+    {
+        Some(i) => Err(anyhow!(
+            "static analysis needs a puzzle program, but instruction {} is '{}'",
+            i,
+            program[i]
+        )),
+        None => Ok(()),
+    }
+}
+
+// Every single jmp <=> nop flip which makes the program terminate.
+//
+// Only instructions on the original execution path can change the outcome,
+// and flipping one terminates exactly when its new successor reaches the exit
+// in the unmodified graph (a path from there can't pass back through the
+// flipped instruction, or the original execution would have terminated too).
+pub fn repair_candidates(program: &[Instruction]) -> Result<Vec<usize>> {
+    check_puzzle_program(program)?;
+    let cfg = Cfg::new(program);
+    let reaches = cfg.reaches_exit();
+    if reaches[0] || program.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(cfg
+        .execution_path()
+        .into_iter()
+        .filter(|i| {
+            program[*i]
+                .flipped()
+                .is_some_and(|f| reaches[successors(*i, &f, program.len())[0]])
+        })
+        .collect())
+}
+
+// the single flip which repairs the program, or an error explaining why there
+// isn't exactly one
+pub fn find_repair(program: &[Instruction]) -> Result<usize> {
+    check_puzzle_program(program)?;
+    if Cfg::new(program).reaches_exit()[0] {
+        return Err(anyhow!("program already terminates"));
+    }
+    match repair_candidates(program)?.as_slice() {
+        [i] => Ok(*i),
+        [] => Err(anyhow!("no single jmp <=> nop flip repairs the program")),
+        many => Err(anyhow!(
+            "{} different flips repair the program, at instructions {:?}",
+            many.len(),
+            many
+        )),
+    }
+}
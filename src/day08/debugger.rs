@@ -0,0 +1,442 @@
+// ==== day 8: interactive debugger
+
+use super::{
+    asm,
+    isa::{self, Reg},
+    snapshot, Console, Guard, Instruction, Status,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+
+const HELP: &str = "\
+commands:
+  s, step [N]           execute N instructions (default 1)
+  c, continue           run until a breakpoint, watch change, or the program stops
+  r, run                reset and continue from pc 0
+  reset                 reset pc, accumulator and hits
+  save FILE             write a snapshot of the console to FILE
+  load FILE             restore the console from a snapshot in FILE
+  limit N|off           halt after N steps, allowing loops; 'off' restores the
+                        puzzle's rule of halting before any repeated instruction
+  b, break pc N         break before executing instruction N
+  b, break acc N        break when the accumulator becomes N
+  b, break              list breakpoints
+  delete N              delete breakpoint N
+  watch pc|acc|REG|hits[N]
+                        show an expression at every stop, and stop when it changes
+  unwatch N             delete watch N
+  hits [N]              show hit counts for instruction N, or all executed instructions
+  patch N OP ARG        replace instruction N, e.g. 'patch 7 nop -4'
+  flip N                exchange jmp <=> nop at instruction N
+  l, list [N]           list the program around instruction N (default pc)
+  i, info               show pc, accumulator, registers, output and status
+  isa                   list the instruction set
+  q, quit               leave the debugger";
+
+enum Breakpoint {
+    Pc(usize),
+    Acc(i32),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Pc(n) => write!(f, "pc == {}", n),
+            Breakpoint::Acc(n) => write!(f, "acc == {}", n),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Watch {
+    Pc,
+    Acc,
+    Reg(Reg),
+    Hits(usize),
+}
+
+impl Watch {
+    fn parse(s: &str, console: &Console) -> Result<Self> {
+        match s {
+            "pc" => Ok(Watch::Pc),
+            "acc" => Ok(Watch::Acc),
+            _ if s.parse::<Reg>().is_ok() => Ok(Watch::Reg(s.parse()?)),
+            _ => {
+                let n: usize = s
+                    .strip_prefix("hits[")
+                    .and_then(|s| s.strip_suffix(']'))
+                    .ok_or_else(|| anyhow!("unknown watch expression '{}'", s))?
+                    .parse()?;
+                if n >= console.program.len() {
+                    return Err(anyhow!("no instruction {}", n));
+                }
+                Ok(Watch::Hits(n))
+            }
+        }
+    }
+
+    fn eval(self, console: &Console) -> i64 {
+        match self {
+            Watch::Pc => console.pc as i64,
+            Watch::Acc => i64::from(console.accumulator),
+            Watch::Reg(r) => i64::from(console.registers[r.0]),
+            Watch::Hits(n) => console.hits[n] as i64,
+        }
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watch::Pc => write!(f, "pc"),
+            Watch::Acc => write!(f, "acc"),
+            Watch::Reg(r) => write!(f, "{}", r),
+            Watch::Hits(n) => write!(f, "hits[{}]", n),
+        }
+    }
+}
+
+// An interactive debugger, driving a Console one step() at a time.
+pub struct Debugger {
+    console: Console,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<(Watch, i64)>, // expression, and its value at the last stop
+    at_breakpoint: Option<usize>, // pc, if the last stop was at a pc breakpoint
+}
+
+impl Debugger {
+    pub fn new(mut console: Console) -> Self {
+        console.reset();
+        Debugger {
+            console,
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            at_breakpoint: None,
+        }
+    }
+
+    // read commands from input until "quit" or end of input
+    pub fn repl(&mut self, input: impl BufRead, mut out: impl Write) -> Result<()> {
+        write!(out, "(dbg) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            match self.command(&line?, &mut out) {
+                Ok(true) => return Ok(()),
+                Ok(false) => (),
+                Err(e) => writeln!(out, "error: {}", e)?,
+            }
+            write!(out, "(dbg) ")?;
+            out.flush()?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    // run one command, returning true to quit
+    fn command(&mut self, line: &str, out: &mut impl Write) -> Result<bool> {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args.as_slice() {
+            [] => (),
+            ["s"] | ["step"] => self.step(1, out)?,
+            ["s", n] | ["step", n] => self.step(n.parse()?, out)?,
+            ["c"] | ["continue"] => self.cont(out)?,
+            ["r"] | ["run"] => {
+                self.reset();
+                self.cont(out)?;
+            }
+            ["reset"] => {
+                self.reset();
+                self.show_state(out)?;
+            }
+            ["b"] | ["break"] => {
+                for (i, b) in self.breakpoints.iter().enumerate() {
+                    writeln!(out, "{}: {}", i, b)?;
+                }
+            }
+            ["b", "pc", n] | ["break", "pc", n] => {
+                self.breakpoints.push(Breakpoint::Pc(n.parse()?));
+            }
+            ["b", "acc", n] | ["break", "acc", n] => {
+                self.breakpoints.push(Breakpoint::Acc(n.parse()?));
+            }
+            ["delete", n] => {
+                let n: usize = n.parse()?;
+                if n >= self.breakpoints.len() {
+                    return Err(anyhow!("no breakpoint {}", n));
+                }
+                self.breakpoints.remove(n);
+            }
+            ["watch", expr] => {
+                let watch = Watch::parse(expr, &self.console)?;
+                let value = watch.eval(&self.console);
+                writeln!(out, "{}: {} = {}", self.watches.len(), watch, value)?;
+                self.watches.push((watch, value));
+            }
+            ["unwatch", n] => {
+                let n: usize = n.parse()?;
+                if n >= self.watches.len() {
+                    return Err(anyhow!("no watch {}", n));
+                }
+                self.watches.remove(n);
+            }
+            ["hits"] => {
+                for (i, hits) in self.console.hits.iter().enumerate() {
+                    if *hits > 0 {
+                        writeln!(
+                            out,
+                            "{:>4}: {:<10} {} hits",
+                            i, self.console.program[i], hits
+                        )?;
+                    }
+                }
+            }
+            ["hits", n] => {
+                let n = self.instruction_index(n)?;
+                let (instruction, hits) = (self.console.program[n], self.console.hits[n]);
+                writeln!(out, "{:>4}: {:<10} {} hits", n, instruction, hits)?;
+            }
+            ["patch", n, opcode, operand] => {
+                let n = self.instruction_index(n)?;
+                self.console.program[n] = Instruction::new(opcode, operand.parse()?)?;
+                writeln!(out, "{:>4}: {}", n, self.console.program[n])?;
+            }
+            ["flip", n] => {
+                let n = self.instruction_index(n)?;
+                let instruction = self.console.program[n];
+                self.console.program[n] = instruction.flipped().ok_or_else(|| {
+                    anyhow!(
+                        "can't flip {}: only jmp and nop can be flipped",
+                        instruction.name()
+                    )
+                })?;
+                writeln!(out, "{:>4}: {}", n, self.console.program[n])?;
+            }
+            ["l"] | ["list"] => self.list(self.console.pc, out)?,
+            ["l", n] | ["list", n] => self.list(n.parse()?, out)?,
+            ["i"] | ["info"] => {
+                self.show_state(out)?;
+                let registers: Vec<String> = isa::REGISTERS
+                    .iter()
+                    .zip(self.console.registers.iter())
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                writeln!(out, "registers: {}", registers.join(" "))?;
+                writeln!(out, "output: {:?}", self.console.output)?;
+                writeln!(out, "steps: {}", self.console.steps)?;
+            }
+            ["save", filename] => {
+                snapshot::save(&self.console.snapshot(), filename)?;
+                writeln!(out, "snapshot written to {}", filename)?;
+            }
+            ["load", filename] => {
+                self.restore(&snapshot::load(filename)?);
+                self.show_state(out)?;
+            }
+            ["limit", "off"] => self.console.guard = Guard::RepeatedInstruction,
+            ["limit", n] => self.console.guard = Guard::StepLimit(n.parse()?),
+            ["isa"] => write!(out, "{}", isa::describe())?,
+            ["h"] | ["help"] => writeln!(out, "{}", HELP)?,
+            ["q"] | ["quit"] => return Ok(true),
+            _ => return Err(anyhow!("unknown command '{}' (try 'help')", line.trim())),
+        }
+        Ok(false)
+    }
+
+    fn instruction_index(&self, s: &str) -> Result<usize> {
+        let n: usize = s.parse()?;
+        if n < self.console.program.len() {
+            Ok(n)
+        } else {
+            Err(anyhow!("no instruction {}", n))
+        }
+    }
+
+    fn reset(&mut self) {
+        self.console.reset();
+        self.at_breakpoint = None;
+        for (watch, value) in self.watches.iter_mut() {
+            *value = watch.eval(&self.console);
+        }
+    }
+
+    // the snapshot may hold a different program, so drop any hit count
+    // watches which no longer refer to an instruction
+    fn restore(&mut self, snapshot: &snapshot::Snapshot) {
+        self.console.restore(snapshot);
+        self.at_breakpoint = None;
+        let n = self.console.program.len();
+        self.watches
+            .retain(|(watch, _)| !matches!(watch, Watch::Hits(i) if *i >= n));
+        for (watch, value) in self.watches.iter_mut() {
+            *value = watch.eval(&self.console);
+        }
+    }
+
+    // execute up to n instructions, stopping early if the program stops
+    fn step(&mut self, n: usize, out: &mut impl Write) -> Result<()> {
+        self.at_breakpoint = None;
+        for _ in 0..n {
+            if self.console.step() != Status::Running {
+                break;
+            }
+        }
+        self.update_watches(out)?;
+        self.show_state(out)
+    }
+
+    // execute until a breakpoint or watch triggers, or the program stops. A pc
+    // breakpoint stops before its instruction runs; the one a continue resumes
+    // from is passed over, so the program can move on.
+    fn cont(&mut self, out: &mut impl Write) -> Result<()> {
+        let mut resuming = self.at_breakpoint.take() == Some(self.console.pc);
+        loop {
+            if !resuming {
+                let pc = self.console.pc;
+                if let Some(i) = self
+                    .breakpoints
+                    .iter()
+                    .position(|b| matches!(b, Breakpoint::Pc(n) if *n == pc))
+                {
+                    writeln!(out, "breakpoint {}: {}", i, self.breakpoints[i])?;
+                    self.at_breakpoint = Some(pc);
+                    break;
+                }
+            }
+            resuming = false;
+            let prev_acc = self.console.accumulator;
+            if self.console.step() != Status::Running {
+                break;
+            }
+            if self.update_watches(out)? {
+                break;
+            }
+            let acc = self.console.accumulator;
+            if let Some(i) = self
+                .breakpoints
+                .iter()
+                .position(|b| matches!(b, Breakpoint::Acc(n) if acc == *n && prev_acc != *n))
+            {
+                writeln!(out, "breakpoint {}: {}", i, self.breakpoints[i])?;
+                break;
+            }
+        }
+        self.show_state(out)
+    }
+
+    // report any watch whose value changed, returning true if one did
+    fn update_watches(&mut self, out: &mut impl Write) -> Result<bool> {
+        let mut changed = false;
+        for (i, (watch, value)) in self.watches.iter_mut().enumerate() {
+            let new_value = watch.eval(&self.console);
+            if new_value != *value {
+                writeln!(out, "watch {}: {}: {} -> {}", i, watch, value, new_value)?;
+                *value = new_value;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    fn show_state(&self, out: &mut impl Write) -> Result<()> {
+        let c = &self.console;
+        match c.get_run_state() {
+            Status::Running => writeln!(
+                out,
+                "pc={} acc={}  next: {}",
+                c.pc, c.accumulator, c.program[c.pc]
+            )?,
+            Status::Completed => writeln!(out, "completed: pc={} acc={}", c.pc, c.accumulator)?,
+            Status::Halted => match c.guard {
+                Guard::RepeatedInstruction => writeln!(
+                    out,
+                    "halted: instruction {} ({}) would run a second time; acc={}",
+                    c.pc, c.program[c.pc], c.accumulator
+                )?,
+                Guard::StepLimit(n) => writeln!(
+                    out,
+                    "halted: step limit of {} reached at pc={}; acc={}",
+                    n, c.pc, c.accumulator
+                )?,
+            },
+            Status::Trapped(trap) => writeln!(
+                out,
+                "trapped: {} at instruction {} ({}); acc={}",
+                trap, c.pc, c.program[c.pc], c.accumulator
+            )?,
+        }
+        for (watch, value) in self.watches.iter() {
+            writeln!(out, "  {} = {}", watch, value)?;
+        }
+        Ok(())
+    }
+
+    // disassemble a few instructions either side of n, marking pc with "=>"
+    // and breakpoints with "*"
+    fn list(&self, n: usize, out: &mut impl Write) -> Result<()> {
+        let disassembly = asm::disassemble(&self.console.program);
+        for (i, line) in disassembly
+            .lines()
+            .enumerate()
+            .skip(n.saturating_sub(5))
+            .take(11)
+        {
+            let marker = if i == self.console.pc { "=>" } else { "  " };
+            let breakpoint = if self
+                .breakpoints
+                .iter()
+                .any(|b| matches!(b, Breakpoint::Pc(p) if *p == i))
+            {
+                "*"
+            } else {
+                " "
+            };
+            writeln!(out, "{}{} {}", marker, breakpoint, line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const PROGRAM: &str =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6\n";
+
+    // run commands through the debugger, returning its output
+    fn session(commands: &str) -> String {
+        let console = Console::from_str(PROGRAM).unwrap();
+        let mut out = Vec::new();
+        Debugger::new(console)
+            .repl(commands.as_bytes(), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn breakpoints_stop_before_their_instruction() {
+        let out = session("break pc 0\nr\nbreak pc 2\nc\nc\n").replace("(dbg) ", "");
+        let lines: Vec<&str> = out.trim_end().lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "breakpoint 0: pc == 0",
+                "pc=0 acc=0  next: nop +0",
+                "breakpoint 1: pc == 2",
+                "pc=2 acc=1  next: jmp +4",
+                "halted: instruction 1 (acc +1) would run a second time; acc=5",
+            ]
+        );
+    }
+
+    #[test]
+    fn flip_names_the_instruction() {
+        let out = session("flip 1\nflip 0\n");
+        assert!(out.contains("error: can't flip acc: only jmp and nop can be flipped"));
+        assert!(out.contains("   0: jmp +0"));
+    }
+}
@@ -0,0 +1,73 @@
+// ==== day 8: Graphviz export of the control flow graph
+//
+// One node per instruction plus an exit node. Fall-through edges are solid and
+// jumps dashed; instructions the console has executed (and the edges between
+// them) are drawn in blue, and the jmp/nop flip which repairs the program, if
+// there is exactly one, is drawn in red.
+
+use super::{cfg, Console, Instruction, Status};
+use crate::utils::{Attrs, Digraph};
+
+const EXECUTED: &str = "blue";
+const REPAIR: &str = "red";
+
+pub fn to_dot(console: &Console) -> String {
+    let program = &console.program;
+    let graph = cfg::Cfg::new(program);
+    let exit = graph.exit();
+    let repair = cfg::find_repair(program).ok();
+    let executed = |node: usize| match node {
+        n if n == exit => console.get_run_state() == Status::Completed,
+        n => console.hits[n] > 0,
+    };
+    let id = |node: usize| match node {
+        n if n == exit => "exit".to_string(),
+        n => n.to_string(),
+    };
+
+    let mut dot = Digraph::new("console");
+    dot.graph_attr("rankdir", "TB");
+    dot.node_default("shape", "box");
+    dot.node_default("fontname", "monospace");
+    for (i, instruction) in program.iter().enumerate() {
+        let mut label = format!("{}: {}", i, instruction);
+        let mut attrs: Attrs = Vec::new();
+        if console.hits[i] > 0 {
+            label += &format!("\nhits {}", console.hits[i]);
+            attrs.push(("color", EXECUTED.to_string()));
+            attrs.push(("penwidth", "2".to_string()));
+        }
+        if repair == Some(i) {
+            label += &format!("\nrepair: {}", instruction.flipped().unwrap());
+            attrs.push(("style", "filled".to_string()));
+            attrs.push(("fillcolor", REPAIR.to_string()));
+            attrs.push(("fontcolor", "white".to_string()));
+        }
+        attrs.insert(0, ("label", label));
+        dot.node(&id(i), attrs);
+    }
+    let mut exit_attrs: Attrs = vec![("shape", "doublecircle".to_string())];
+    if executed(exit) {
+        exit_attrs.push(("color", EXECUTED.to_string()));
+        exit_attrs.push(("penwidth", "2".to_string()));
+    }
+    dot.node("exit", exit_attrs);
+
+    for (i, instruction) in program.iter().enumerate() {
+        // a conditional jump's successors are the fall-through, then the jump
+        for (k, &s) in graph.successors(i).iter().enumerate() {
+            let mut attrs: Attrs = Vec::new();
+            if matches!(instruction, Instruction::Jmp(_)) || k == 1 {
+                attrs.push(("style", "dashed".to_string()));
+            }
+            // hits can't say which way a conditional jump went, so this
+            // highlights every edge between executed instructions
+            if executed(i) && executed(s) {
+                attrs.push(("color", EXECUTED.to_string()));
+                attrs.push(("penwidth", "2".to_string()));
+            }
+            dot.edge(&id(i), &id(s), attrs);
+        }
+    }
+    dot.to_string()
+}
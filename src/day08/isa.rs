@@ -0,0 +1,205 @@
+// ==== day 8: instruction set
+//
+// The puzzle's console only has acc, jmp and nop. The extended set adds
+// general purpose registers, arithmetic on the accumulator, conditional jumps
+// and output, for programs beyond the puzzle. The table below is the single
+// definition of every opcode's name and operand; the assembler, disassembler
+// and debugger all go through it.
+
+use anyhow::{anyhow, Result};
+use std::{fmt, str::FromStr};
+
+// names of the general purpose registers, in register number order
+pub const REGISTERS: [&str; 4] = ["a", "b", "c", "d"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reg(pub usize);
+
+impl FromStr for Reg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        REGISTERS
+            .iter()
+            .position(|r| *r == s)
+            .map(Reg)
+            .ok_or_else(|| anyhow!("unknown register '{}'", s))
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REGISTERS[self.0])
+    }
+}
+
+// an immediate value or a register
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operand {
+    Imm(i32),
+    Reg(Reg),
+}
+
+impl FromStr for Operand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.parse::<i32>() {
+            Ok(n) => Ok(Operand::Imm(n)),
+            Err(_) => Ok(Operand::Reg(s.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Imm(n) => write!(f, "{:+}", n),
+            Operand::Reg(r) => write!(f, "{}", r),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InstructionSet {
+    Puzzle,
+    Extended,
+}
+
+// what an opcode's single operand may be
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperandKind {
+    Immediate, // a signed number
+    Offset,    // a signed jump offset relative to the instruction (or a label)
+    Value,     // an immediate or a register
+    Register,  // a register
+}
+
+pub struct OpcodeDef {
+    pub name: &'static str,
+    pub operand: OperandKind,
+    pub set: InstructionSet,
+    pub summary: &'static str,
+}
+
+pub const INSTRUCTION_SET: &[OpcodeDef] = &[
+    OpcodeDef {
+        name: "acc",
+        operand: OperandKind::Immediate,
+        set: InstructionSet::Puzzle,
+        summary: "acc += n",
+    },
+    OpcodeDef {
+        name: "jmp",
+        operand: OperandKind::Offset,
+        set: InstructionSet::Puzzle,
+        summary: "jump by n",
+    },
+    OpcodeDef {
+        name: "nop",
+        operand: OperandKind::Immediate,
+        set: InstructionSet::Puzzle,
+        summary: "do nothing",
+    },
+    OpcodeDef {
+        name: "add",
+        operand: OperandKind::Value,
+        set: InstructionSet::Extended,
+        summary: "acc += x",
+    },
+    OpcodeDef {
+        name: "sub",
+        operand: OperandKind::Value,
+        set: InstructionSet::Extended,
+        summary: "acc -= x",
+    },
+    OpcodeDef {
+        name: "mul",
+        operand: OperandKind::Value,
+        set: InstructionSet::Extended,
+        summary: "acc *= x",
+    },
+    OpcodeDef {
+        name: "div",
+        operand: OperandKind::Value,
+        set: InstructionSet::Extended,
+        summary: "acc /= x (truncating)",
+    },
+    OpcodeDef {
+        name: "ld",
+        operand: OperandKind::Value,
+        set: InstructionSet::Extended,
+        summary: "acc = x",
+    },
+    OpcodeDef {
+        name: "st",
+        operand: OperandKind::Register,
+        set: InstructionSet::Extended,
+        summary: "r = acc",
+    },
+    OpcodeDef {
+        name: "jz",
+        operand: OperandKind::Offset,
+        set: InstructionSet::Extended,
+        summary: "jump by n if acc == 0",
+    },
+    OpcodeDef {
+        name: "jnz",
+        operand: OperandKind::Offset,
+        set: InstructionSet::Extended,
+        summary: "jump by n if acc != 0",
+    },
+    OpcodeDef {
+        name: "out",
+        operand: OperandKind::Value,
+        set: InstructionSet::Extended,
+        summary: "write x to the output",
+    },
+];
+
+pub fn lookup(name: &str) -> Result<&'static OpcodeDef> {
+    INSTRUCTION_SET
+        .iter()
+        .find(|def| def.name == name)
+        .ok_or_else(|| anyhow!("unknown instruction '{}'", name))
+}
+
+// check an operand against what an opcode accepts
+pub fn check_operand(def: &OpcodeDef, operand: Operand) -> Result<()> {
+    match (def.operand, operand) {
+        (OperandKind::Immediate, Operand::Imm(_))
+        | (OperandKind::Offset, Operand::Imm(_))
+        | (OperandKind::Value, _)
+        | (OperandKind::Register, Operand::Reg(_)) => Ok(()),
+        _ => Err(anyhow!(
+            "'{}' takes {}, not '{}'",
+            def.name,
+            match def.operand {
+                OperandKind::Immediate => "a number",
+                OperandKind::Offset => "a jump offset",
+                OperandKind::Register => "a register",
+                OperandKind::Value => "a number or register",
+            },
+            operand
+        )),
+    }
+}
+
+// one line per opcode, for help text
+pub fn describe() -> String {
+    INSTRUCTION_SET
+        .iter()
+        .map(|def| {
+            let set = match def.set {
+                InstructionSet::Puzzle => "",
+                InstructionSet::Extended => " (extended)",
+            };
+            let operand = match def.operand {
+                OperandKind::Immediate | OperandKind::Offset => "n",
+                OperandKind::Value => "x",
+                OperandKind::Register => "r",
+            };
+            format!("  {:<4} {}   {}{}\n", def.name, operand, def.summary, set)
+        })
+        .collect()
+}
@@ -0,0 +1,180 @@
+// ==== day 8: console snapshots
+//
+// A snapshot is everything needed to resume execution exactly where it left
+// off: the program (which may have been patched), its hit counts, and the
+// machine state. The guard and any trace being recorded belong to the console
+// rather than the snapshot.
+
+use super::{isa, Console, Instruction, Trap};
+use crate::utils::{self, ParseError};
+use anyhow::{anyhow, Result};
+use std::{convert::TryInto, fmt, fs};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub pc: usize,
+    pub accumulator: i32,
+    pub registers: [i32; isa::REGISTERS.len()],
+    pub output: Vec<i32>,
+    pub steps: usize,
+    pub trap: Option<Trap>,
+    pub hits: Vec<usize>,
+    pub program: Vec<Instruction>,
+}
+
+impl Console {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            accumulator: self.accumulator,
+            registers: self.registers,
+            output: self.output.clone(),
+            steps: self.steps,
+            trap: self.trap,
+            hits: self.hits.clone(),
+            program: self.program.clone(),
+        }
+    }
+
+    // return to a snapshot, rewinding any trace being recorded to match
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.pc = snapshot.pc;
+        self.accumulator = snapshot.accumulator;
+        self.registers = snapshot.registers;
+        self.output.clone_from(&snapshot.output);
+        self.steps = snapshot.steps;
+        self.trap = snapshot.trap;
+        self.hits.clone_from(&snapshot.hits);
+        self.program.clone_from(&snapshot.program);
+        if let Some(trace) = &mut self.trace {
+            trace.truncate(snapshot.steps);
+        }
+    }
+}
+
+fn trap_name(trap: Option<Trap>) -> &'static str {
+    match trap {
+        None => "none",
+        Some(Trap::Overflow) => "overflow",
+        Some(Trap::DivideByZero) => "divide-by-zero",
+    }
+}
+
+fn join(values: &[i32]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    values.join(" ")
+}
+
+// one "key: value" line per field, then the program with one "hits
+// instruction" line per instruction
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# day 8 console snapshot")?;
+        writeln!(f, "pc: {}", self.pc)?;
+        writeln!(f, "accumulator: {}", self.accumulator)?;
+        writeln!(f, "registers: {}", join(&self.registers))?;
+        writeln!(f, "output: {}", join(&self.output))?;
+        writeln!(f, "steps: {}", self.steps)?;
+        writeln!(f, "trap: {}", trap_name(self.trap))?;
+        writeln!(f, "program:")?;
+        for (hits, instruction) in self.hits.iter().zip(&self.program) {
+            writeln!(f, "{:>8} {}", hits, instruction)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_values(s: &str) -> Result<Vec<i32>> {
+    s.split_whitespace()
+        .map(|v| v.parse().map_err(|_| anyhow!("bad value '{}'", v)))
+        .collect()
+}
+
+// the header fields, in the order they're written
+const FIELDS: [&str; 7] = [
+    "pc",
+    "accumulator",
+    "registers",
+    "output",
+    "steps",
+    "trap",
+    "program",
+];
+
+// parse a snapshot written by Display
+pub fn parse(s: &str) -> Result<Snapshot, ParseError> {
+    let mut snapshot = Snapshot {
+        pc: 0,
+        accumulator: 0,
+        registers: Default::default(),
+        output: Vec::new(),
+        steps: 0,
+        trap: None,
+        hits: Vec::new(),
+        program: Vec::new(),
+    };
+    let mut fields = FIELDS.iter();
+    let mut in_program = false;
+    for (i, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed: Result<()> = (|| {
+            if in_program {
+                let (hits, instruction) = line
+                    .split_once(' ')
+                    .ok_or_else(|| anyhow!("expected hit count and instruction"))?;
+                snapshot.hits.push(hits.parse()?);
+                snapshot.program.push(instruction.parse()?);
+                return Ok(());
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("expected 'key: value'"))?;
+            let value = value.trim();
+            match fields.next() {
+                Some(field) if *field == key => (),
+                Some(field) => return Err(anyhow!("expected '{}', found '{}'", field, key)),
+                None => unreachable!("no fields follow the program"),
+            }
+            match key {
+                "pc" => snapshot.pc = value.parse()?,
+                "accumulator" => snapshot.accumulator = value.parse()?,
+                "registers" => {
+                    snapshot.registers = parse_values(value)?
+                        .try_into()
+                        .map_err(|_| anyhow!("expected {} registers", isa::REGISTERS.len()))?
+                }
+                "output" => snapshot.output = parse_values(value)?,
+                "steps" => snapshot.steps = value.parse()?,
+                "trap" => {
+                    snapshot.trap = match value {
+                        "none" => None,
+                        "overflow" => Some(Trap::Overflow),
+                        "divide-by-zero" => Some(Trap::DivideByZero),
+                        _ => return Err(anyhow!("unknown trap '{}'", value)),
+                    }
+                }
+                _ => in_program = true,
+            }
+            Ok(())
+        })();
+        parsed.map_err(|e| ParseError::new(i + 1, e))?;
+    }
+    match fields.next() {
+        None => Ok(snapshot),
+        Some(field) => Err(ParseError::new(
+            s.lines().count(),
+            format!("missing '{}'", field),
+        )),
+    }
+}
+
+pub fn save(snapshot: &Snapshot, filename: &str) -> Result<()> {
+    Ok(fs::write(filename, snapshot.to_string())?)
+}
+
+pub fn load(filename: &str) -> Result<Snapshot> {
+    Ok(parse(&utils::read_file(filename)?)?)
+}
@@ -0,0 +1,288 @@
+// ==== day 8: execution traces
+
+use super::{Console, Instruction, Status};
+use crate::utils::{self, FromCaptures, ParseError};
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::{
+    fmt::{self, Write},
+    fs,
+};
+
+// One executed instruction: the step number (0-based), where it was and what
+// it did to the accumulator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceEntry {
+    pub step: usize,
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub acc_before: i32,
+    pub acc_after: i32,
+}
+
+// A recorded run: the changes made to the program before it ran, such as the
+// instruction a repair flipped, and every instruction executed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+    pub patches: Vec<(usize, Instruction)>, // (index, new instruction)
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    // the trace of a run of patched, which started out as original
+    pub fn new(
+        original: &[Instruction],
+        patched: &[Instruction],
+        entries: Vec<TraceEntry>,
+    ) -> Self {
+        let patches = original
+            .iter()
+            .zip(patched)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, (_, b))| (i, *b))
+            .collect();
+        Trace { patches, entries }
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (acc {} -> {})",
+            self.pc, self.instruction, self.acc_before, self.acc_after
+        )
+    }
+}
+
+static TEXT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(?P<step>\d+)\s+(?P<pc>\d+)\s+(?P<instruction>\w+ \S+)\s+(?P<acc_before>-?\d+)\s+(?P<acc_after>-?\d+)\s*$")
+        .expect("regex compilation failure")
+});
+// one entry per line, as to_json() writes it, though spacing may vary
+static JSON_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*\{\s*"step"\s*:\s*(?P<step>\d+)\s*,\s*"pc"\s*:\s*(?P<pc>\d+)\s*,\s*"instruction"\s*:\s*"(?P<instruction>[^"]*)"\s*,\s*"acc_before"\s*:\s*(?P<acc_before>-?\d+)\s*,\s*"acc_after"\s*:\s*(?P<acc_after>-?\d+)\s*\}\s*,?\s*$"#)
+        .expect("regex compilation failure")
+});
+static TEXT_PATCH_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^#\s*patch\s+(?P<index>\d+)\s+(?P<instruction>\w+ \S+)\s*$")
+        .expect("regex compilation failure")
+});
+static JSON_PATCH_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*\{\s*"index"\s*:\s*(?P<index>\d+)\s*,\s*"instruction"\s*:\s*"(?P<instruction>[^"]*)"\s*\}\s*,?\s*$"#)
+        .expect("regex compilation failure")
+});
+// the lines around the patches and entries: '{"patches":[', '],"trace":[',
+// '{"patches":[],"trace":[' or '{"trace":[' (when there are no patches), ']}'
+// or blank
+static JSON_STRUCTURE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*(\{\s*"patches"\s*:\s*\[|(\{|\]\s*,|\{\s*"patches"\s*:\s*\[\s*\]\s*,)\s*"trace"\s*:\s*\[|\]\s*\})?\s*$"#)
+        .expect("regex compilation failure")
+});
+
+// a patch from the captures of TEXT_PATCH_RE or JSON_PATCH_RE
+fn patch_from_captures(caps: &Captures) -> Result<(usize, Instruction)> {
+    Ok((
+        utils::capture(caps, "index")?,
+        utils::capture(caps, "instruction")?,
+    ))
+}
+
+impl FromCaptures for TraceEntry {
+    fn from_captures(caps: &Captures) -> Result<Self> {
+        Ok(TraceEntry {
+            step: utils::capture(caps, "step")?,
+            pc: utils::capture(caps, "pc")?,
+            instruction: utils::capture(caps, "instruction")?,
+            acc_before: utils::capture(caps, "acc_before")?,
+            acc_after: utils::capture(caps, "acc_after")?,
+        })
+    }
+}
+
+// a "# patch N INSTRUCTION" line for each patch, then one entry per line, in
+// columns: step, pc, instruction, accumulator before and after
+pub fn to_text(trace: &Trace) -> String {
+    let mut s = String::new();
+    for (i, instruction) in &trace.patches {
+        writeln!(s, "# patch {} {}", i, instruction).unwrap();
+    }
+    s += "# step     pc  instruction  acc_before  acc_after\n";
+    for e in &trace.entries {
+        writeln!(
+            s,
+            "{:>6} {:>6}  {:<11}  {:>10}  {:>9}",
+            e.step, e.pc, e.instruction, e.acc_before, e.acc_after
+        )
+        .unwrap();
+    }
+    s
+}
+
+// a JSON object with "patches" and "trace" arrays, one element per line
+pub fn to_json(trace: &Trace) -> String {
+    let patches: Vec<String> = trace
+        .patches
+        .iter()
+        .map(|(i, instruction)| format!(r#"  {{"index":{},"instruction":"{}"}}"#, i, instruction))
+        .collect();
+    let entries: Vec<String> = trace
+        .entries
+        .iter()
+        .map(|e| {
+            format!(
+                r#"  {{"step":{},"pc":{},"instruction":"{}","acc_before":{},"acc_after":{}}}"#,
+                e.step, e.pc, e.instruction, e.acc_before, e.acc_after
+            )
+        })
+        .collect();
+    let patches = match patches.as_slice() {
+        [] => String::from("{\"patches\":[],"),
+        _ => format!("{{\"patches\":[\n{}\n],", patches.join(",\n")),
+    };
+    format!("{}\"trace\":[\n{}\n]}}\n", patches, entries.join(",\n"))
+}
+
+// parse a trace written by to_text() or to_json()
+pub fn parse(s: &str) -> Result<Trace, ParseError> {
+    enum Line {
+        Patch(usize, Instruction),
+        Entry(TraceEntry),
+        Other,
+    }
+    let json = s.trim_start().starts_with('{');
+    let (patch_re, entry_re): (&Regex, &Regex) = if json {
+        (&JSON_PATCH_RE, &JSON_RE)
+    } else {
+        (&TEXT_PATCH_RE, &TEXT_RE)
+    };
+    let lines = utils::parse_lines_with(s, |l| {
+        if let Some(caps) = entry_re.captures(l) {
+            return TraceEntry::from_captures(&caps).map(Line::Entry);
+        }
+        if let Some(caps) = patch_re.captures(l) {
+            let (i, instruction) = patch_from_captures(&caps)?;
+            return Ok(Line::Patch(i, instruction));
+        }
+        match l {
+            // skip the JSON object and array lines, and the text header
+            _ if json && JSON_STRUCTURE_RE.is_match(l) => Ok(Line::Other),
+            _ if !json && (l.trim().is_empty() || l.trim_start().starts_with('#')) => {
+                Ok(Line::Other)
+            }
+            _ if json => Err(anyhow!(
+                "expected one patch or trace entry per line, as written by day08 trace, found '{}'",
+                l
+            )),
+            _ => Err(anyhow!("bad trace entry '{}'", l)),
+        }
+    })?;
+    let mut trace = Trace::default();
+    for line in lines {
+        match line {
+            Line::Patch(i, instruction) => trace.patches.push((i, instruction)),
+            Line::Entry(e) => trace.entries.push(e),
+            Line::Other => (),
+        }
+    }
+    Ok(trace)
+}
+
+// write a trace to a file, as JSON if the name ends in ".json" and as text
+// otherwise
+pub fn save(trace: &Trace, filename: &str) -> Result<()> {
+    let s = if filename.ends_with(".json") {
+        to_json(trace)
+    } else {
+        to_text(trace)
+    };
+    Ok(fs::write(filename, s)?)
+}
+
+pub fn load(filename: &str) -> Result<Trace> {
+    Ok(parse(&utils::read_file(filename)?)?)
+}
+
+// Reconstruct the console state after the first `step` entries of a trace,
+// by patching the program as the trace says, re-executing it and checking
+// each step against the trace.
+pub fn replay(program: &[Instruction], trace: &Trace, step: usize) -> Result<Console> {
+    if step > trace.entries.len() {
+        return Err(anyhow!("trace only has {} steps", trace.entries.len()));
+    }
+    let mut program = program.to_vec();
+    for (i, instruction) in &trace.patches {
+        *program.get_mut(*i).ok_or_else(|| {
+            anyhow!(
+                "trace patches instruction {}, which isn't in the program",
+                i
+            )
+        })? = *instruction;
+    }
+    let mut console = Console::from_program(program);
+    for (i, expected) in trace.entries[..step].iter().enumerate() {
+        let (pc, acc_before) = (console.pc, console.accumulator);
+        let instruction = *console
+            .program
+            .get(pc)
+            .ok_or_else(|| anyhow!("step {}: pc {} is outside the program", i, pc))?;
+        if console.step() != Status::Running {
+            return Err(anyhow!("step {}: the program has stopped", i));
+        }
+        // the trace's own step numbers must run 0, 1, 2, ... too, so a gap
+        // or reordering shows up
+        if expected.step != i {
+            return Err(anyhow!(
+                "step {}: trace entry is numbered {}",
+                i,
+                expected.step
+            ));
+        }
+        let actual = TraceEntry {
+            step: i,
+            pc,
+            instruction,
+            acc_before,
+            acc_after: console.accumulator,
+        };
+        if actual != *expected {
+            return Err(anyhow!(
+                "step {}: trace has {}, but the program gives {}",
+                i,
+                expected,
+                actual
+            ));
+        }
+    }
+    Ok(console)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // the puzzle's example; flipping instruction 7 (jmp -4) makes it finish
+    const PROGRAM: &str =
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6\n";
+
+    #[test]
+    fn repaired_trace_replays() {
+        let mut console = Console::from_str(PROGRAM).unwrap();
+        let original = console.program.clone();
+        console.record_trace();
+        assert_eq!(console.repair().unwrap(), 8);
+        let trace = Trace::new(&original, &console.program, console.trace.unwrap());
+        assert_eq!(trace.patches, [(7, Instruction::Nop(-4))]);
+
+        for text in [to_text(&trace), to_json(&trace)] {
+            let parsed = parse(&text).unwrap();
+            assert_eq!(parsed, trace);
+            let replayed = replay(&original, &parsed, parsed.entries.len()).unwrap();
+            assert_eq!(replayed.get_run_state(), Status::Completed);
+            assert_eq!(replayed.accumulator, 8);
+        }
+    }
+}
@@ -2,46 +2,59 @@
 
 use crate::utils;
 use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
 
-fn is_bad(data: &[i64], n: usize, i: usize) -> Result<i64> {
-    let prev_n = &data[i - n..i];
-    if utils::combinations(2, prev_n.len()).any(|c| data[i] == prev_n[c[0]] + prev_n[c[1]]) {
-        Err(anyhow!("no result found"))
-    } else {
-        Ok(data[i])
-    }
+// true if x is the sum of two of the numbers in prev
+fn is_sum_of_pair(prev: &VecDeque<i64>, x: i64) -> bool {
+    utils::combinations(2, prev.len()).any(|c| x == prev[c[0]] + prev[c[1]])
 }
 
-fn find_first_bad(data: &[i64], n: usize, start: usize) -> Result<i64> {
-    for i in start..data.len() {
-        let ret = is_bad(data, n, i);
-        if ret.is_ok() {
-            return ret;
+// stream the data, keeping only the previous n numbers, until finding one
+// which isn't the sum of two of them
+fn find_first_bad(data: impl Iterator<Item = i64>, n: usize) -> Result<i64> {
+    let mut prev_n: VecDeque<i64> = VecDeque::with_capacity(n + 1);
+    for x in data {
+        if prev_n.len() == n {
+            if !is_sum_of_pair(&prev_n, x) {
+                return Ok(x);
+            }
+            prev_n.pop_front();
         }
+        prev_n.push_back(x);
     }
     Err(anyhow!("no result found"))
 }
 
-fn find_contiguous_set(data: &[i64], x: i64) -> Result<i64> {
-    for set_size in 2..data.len() - 1 {
-        for i in 0..data.len() - set_size {
-            if data[i..i + set_size].iter().sum::<i64>() == x {
-                // TODO: this can be optimized by using a rolling window, but for now this is fast enough
-                let min = data[i..i + set_size].iter().min().unwrap();
-                let max = data[i..i + set_size].iter().max().unwrap();
-                return Ok(min + max);
-            }
+// stream the data through a sliding window, growing it at the back while its
+// sum is below x and shrinking it at the front while above; the numbers are
+// all positive, so the window only ever spans a run summing to about x
+fn find_contiguous_set(data: impl Iterator<Item = i64>, x: i64) -> Result<i64> {
+    let mut window: VecDeque<i64> = VecDeque::new();
+    let mut sum = 0;
+    for n in data {
+        window.push_back(n);
+        sum += n;
+        while sum > x {
+            sum -= window.pop_front().unwrap();
+        }
+        if sum == x && window.len() >= 2 {
+            let min = window.iter().min().unwrap();
+            let max = window.iter().max().unwrap();
+            return Ok(min + max);
         }
     }
     Err(anyhow!("no result found"))
 }
 
 pub fn run() -> Result<(i64, i64)> {
-    let data = utils::read_i64s("data/input-09.txt")?;
     let answers = utils::read_i64s("data/output-09.txt")?;
 
-    let p1 = find_first_bad(&data, 25, 25)?;
-    let p2 = find_contiguous_set(&data, p1)?;
+    let p1 = utils::try_stream(utils::stream_parsed("data/input-09.txt")?, |data| {
+        find_first_bad(data, 25)
+    })?;
+    let p2 = utils::try_stream(utils::stream_parsed("data/input-09.txt")?, |data| {
+        find_contiguous_set(data, p1)
+    })?;
 
     assert_eq!(p1, answers[0]);
     assert_eq!(p2, answers[1]);
@@ -1,7 +1,8 @@
 // ==== day 11
 
-use crate::utils;
-use anyhow::Result;
+use crate::utils::{self, Grid, Pos, DIRECTIONS8};
+use anyhow::{anyhow, Result};
+use std::{convert::TryFrom, fmt};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum Location {
@@ -10,128 +11,82 @@ enum Location {
     Occupied,
 }
 
-// compute the 8 immediately adjacent coordinates which contian chairs
-fn adjacent_coords(
-    seat_map: &[Vec<Location>],
-    pos: (usize, usize),
-    max: (usize, usize),
-) -> Vec<(usize, usize)> {
-    let min_row = match pos.0 {
-        0 => 0,
-        _ => pos.0 - 1,
-    };
-    let min_col = match pos.1 {
-        0 => 0,
-        _ => pos.1 - 1,
-    };
-    let max_row = max.0.min(pos.0 + 1);
-    let max_col = max.1.min(pos.1 + 1);
+impl TryFrom<char> for Location {
+    type Error = anyhow::Error;
 
-    (min_row..=max_row)
-        .flat_map(|i| (min_col..=max_col).map(move |j| (i, j)))
-        .filter(|p| *p != pos && seat_map[p.0][p.1] != Location::Floor)
-        .collect()
-}
-
-// precompute all the immediately adjacent chairs for all positions on a map
-fn compute_basic_adjacency(map: &[Vec<Location>]) -> Vec<Vec<Vec<(usize, usize)>>> {
-    (0..map.len())
-        .map(|row| {
-            (0..map[row].len())
-                .map(|col| adjacent_coords(&map, (row, col), (map.len() - 1, map[row].len() - 1)))
-                .collect()
-        })
-        .collect()
+    fn try_from(c: char) -> Result<Self> {
+        match c {
+            '.' => Ok(Location::Floor),
+            'L' => Ok(Location::Unoccupied),
+            '#' => Ok(Location::Occupied),
+            _ => Err(anyhow!("unknown seat map location '{}'", c)),
+        }
+    }
 }
 
-// search in one direction from a position until finding a chair (used to compute
-// advanced adjacency
-fn search_direction_for_chair(
-    map: &[Vec<Location>],
-    start: (usize, usize),
-    step: (i32, i32),
-) -> Option<(usize, usize)> {
-    let mut i = start.0 as i32 + step.0;
-    let mut j = start.1 as i32 + step.1;
-
-    while (0 <= i && i < map.len() as i32) && (0 <= j && j < map[i as usize].len() as i32) {
-        if map[i as usize][j as usize] != Location::Floor {
-            return Some((i as usize, j as usize));
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Floor => write!(f, "."),
+            Location::Unoccupied => write!(f, "L"),
+            Location::Occupied => write!(f, "#"),
         }
-        i += step.0;
-        j += step.1;
     }
-    None
 }
 
-// precompute all the directionally adjacent chairs for all positions on a map
-fn compute_advanced_adjacency(map: &[Vec<Location>]) -> Vec<Vec<Vec<(usize, usize)>>> {
-    // figure out our eight direction step values
-    let dirs: Vec<(i32, i32)> = (-1..2)
-        .flat_map(|i| (-1..2).map(move |j| (i, j)))
-        .filter(|p| *p != (0, 0))
-        .collect();
+// precompute all the immediately adjacent chairs for all positions on a map
+fn compute_basic_adjacency(map: &Grid<Location>) -> Grid<Vec<Pos>> {
+    map.map(|pos, _| {
+        map.neighbours8(pos)
+            .filter(|p| map[*p] != Location::Floor)
+            .collect()
+    })
+}
 
-    // for every cell, search for a chair in the eight directions
-    (0..map.len())
-        .map(|i| {
-            (0..map[i].len())
-                .map(|j| {
-                    dirs.iter()
-                        .filter_map(move |d| {
-                            let dir = *d;
-                            search_direction_for_chair(map, (i, j), dir)
-                        })
-                        .collect()
-                })
-                .collect()
-        })
-        .collect::<Vec<Vec<Vec<(usize, usize)>>>>()
+// precompute all the directionally adjacent chairs for all positions on a map,
+// by searching in each of the eight directions until finding a chair
+fn compute_advanced_adjacency(map: &Grid<Location>) -> Grid<Vec<Pos>> {
+    map.map(|pos, _| {
+        DIRECTIONS8
+            .iter()
+            .filter_map(|dir| map.cast_ray(pos, *dir, |l| *l != Location::Floor))
+            .collect()
+    })
 }
 
 // run the seating algorithm, return the total number of changes
 fn seating_round(
-    map: &mut Vec<Vec<Location>>,
-    adj: &[Vec<Vec<(usize, usize)>>],
+    map: &mut Grid<Location>,
+    adj: &Grid<Vec<Pos>>,
     occupied_threshold: usize,
 ) -> usize {
-    let mut changes: Vec<(usize, usize, Location)> = Vec::new();
-
-    for row in 0..map.len() {
-        for col in 0..map[row].len() {
-            match map[row].get(col).unwrap() {
-                Location::Floor => (),
-                Location::Unoccupied => {
-                    if !adj[row][col]
-                        .iter()
-                        .any(|l| map[l.0][l.1] == Location::Occupied)
-                    {
-                        changes.push((row, col, Location::Occupied));
-                    }
-                }
-                Location::Occupied => {
-                    if adj[row][col]
-                        .iter()
-                        .filter(|l| map[l.0][l.1] == Location::Occupied)
-                        .count()
-                        >= occupied_threshold
-                    {
-                        changes.push((row, col, Location::Unoccupied));
-                    }
-                }
+    let changes: Vec<(Pos, Location)> = map
+        .iter()
+        .filter_map(|(pos, location)| {
+            let occupied = || {
+                adj[pos]
+                    .iter()
+                    .filter(|p| map[**p] == Location::Occupied)
+                    .count()
             };
-        }
-    }
+            match location {
+                Location::Floor => None,
+                Location::Unoccupied if occupied() == 0 => Some((pos, Location::Occupied)),
+                Location::Occupied if occupied() >= occupied_threshold => {
+                    Some((pos, Location::Unoccupied))
+                }
+                _ => None,
+            }
+        })
+        .collect();
 
-    changes.iter().for_each(|c| map[c.0][c.1] = c.2);
+    changes.iter().for_each(|(pos, l)| map[*pos] = *l);
     changes.len()
 }
 
-// coutnt occupised seats on a given map
-fn count_occupied(map: &[Vec<Location>]) -> usize {
-    map.iter()
-        .flat_map(|row| row.iter().filter(|cell| **cell == Location::Occupied))
-        .count()
+// count occupied seats on a given map
+fn count_occupied(map: &Grid<Location>) -> usize {
+    map.count(|l| *l == Location::Occupied)
 }
 
 // main
@@ -139,19 +94,8 @@ pub fn run() -> Result<(usize, usize)> {
     let data = utils::read_file("data/input-11.txt")?;
     let answers = utils::read_i64s("data/output-11.txt")?;
 
-    // parse into a 2D vector of Location{}
-    let starting_map: Vec<Vec<Location>> = data
-        .lines()
-        .map(|l| {
-            l.chars()
-                .map(|c| match c {
-                    'L' => Location::Unoccupied,
-                    '.' => Location::Floor,
-                    _ => unreachable!("bad input"),
-                })
-                .collect()
-        })
-        .collect();
+    // parse into a 2D grid of Location{}
+    let starting_map: Grid<Location> = Grid::parse(&data)?;
 
     // run seating alogirthm using basic adjacency and occupied threshold of 4
     let mut map1 = starting_map.clone();
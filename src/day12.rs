@@ -1,66 +1,103 @@
 // ==== day 12
 
 use crate::utils;
-use anyhow::Result;
-use std::mem;
+use anyhow::{anyhow, Result};
+use std::{mem, str::FromStr};
 
-fn navigate(data: &str) -> usize {
+// one navigation instruction, like "F10" or "R90"; turns are kept in 0..360
+struct Step {
+    action: char,
+    value: i64,
+}
+
+impl FromStr for Step {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        let action = chars
+            .next()
+            .ok_or_else(|| anyhow!("empty navigation step"))?;
+        let value: i64 = chars
+            .as_str()
+            .parse()
+            .map_err(|e| anyhow!("bad amount in '{}': {}", s.trim(), e))?;
+        match action {
+            'N' | 'S' | 'E' | 'W' | 'F' => Ok(Step { action, value }),
+            'L' | 'R' if value % 90 == 0 => Ok(Step {
+                action,
+                value: value.rem_euclid(360),
+            }),
+            'L' | 'R' => Err(anyhow!(
+                "can only turn by a multiple of 90 degrees, not {}",
+                value
+            )),
+            _ => Err(anyhow!(
+                "unknown action '{}': expected one of N, S, E, W, L, R or F",
+                action
+            )),
+        }
+    }
+}
+
+fn navigate(steps: impl Iterator<Item = Step>) -> usize {
     let mut heading = 90;
     let (mut pos_n, mut pos_e) = (0, 0);
-    data.lines().for_each(|l| {
-        let dir = &l[0..1];
-        let dist = &l[1..].parse::<i64>().expect("int parse error");
+    steps.for_each(|step| {
+        let dir = step.action;
+        let dist = &step.value;
 
         match dir {
-            "N" => pos_n += dist,
-            "S" => pos_n -= dist,
-            "E" => pos_e += dist,
-            "W" => pos_e -= dist,
-            "F" => match heading {
+            'N' => pos_n += dist,
+            'S' => pos_n -= dist,
+            'E' => pos_e += dist,
+            'W' => pos_e -= dist,
+            'F' => match heading {
                 0 | 360 => pos_n += dist,
                 90 => pos_e += dist,
                 180 => pos_n -= dist,
                 270 => pos_e -= dist,
-                _ => panic!("non cardinal heading!"),
+                _ => unreachable!("turns are multiples of 90"),
             },
-            "L" => heading = (heading + 360 - dist) % 360,
-            "R" => heading = (heading + dist) % 360,
-            _ => panic!("bad direction!"),
+            'L' => heading = (heading + 360 - dist) % 360,
+            'R' => heading = (heading + dist) % 360,
+            _ => unreachable!("actions are checked by Step::from_str"),
         }
     });
 
     (pos_n.abs() + pos_e.abs()) as usize
 }
 
-fn navigate2(data: &str) -> usize {
+fn navigate2(steps: impl Iterator<Item = Step>) -> usize {
     let (mut wpt_n, mut wpt_e) = (1, 10);
     let (mut ship_n, mut ship_e) = (0, 0);
-    data.lines().for_each(|l| {
-        let dir = &l[0..1];
-        let dist = &l[1..].parse::<i64>().expect("int parse error");
+    steps.for_each(|step| {
+        let dir = step.action;
+        let dist = &step.value;
 
         match (dir, dist) {
-            ("N", _) => wpt_n += dist,
-            ("S", _) => wpt_n -= dist,
-            ("E", _) => wpt_e += dist,
-            ("W", _) => wpt_e -= dist,
-            ("F", _) => {
+            ('N', _) => wpt_n += dist,
+            ('S', _) => wpt_n -= dist,
+            ('E', _) => wpt_e += dist,
+            ('W', _) => wpt_e -= dist,
+            ('F', _) => {
                 ship_n += dist * wpt_n;
                 ship_e += dist * wpt_e;
             }
-            ("L", 90) | ("R", 270) => {
+            ('L', 90) | ('R', 270) => {
                 wpt_n = -wpt_n;
                 mem::swap(&mut wpt_n, &mut wpt_e);
             }
-            ("L", 270) | ("R", 90) => {
+            ('L', 270) | ('R', 90) => {
                 wpt_e = -wpt_e;
                 mem::swap(&mut wpt_n, &mut wpt_e);
             }
-            ("L", 180) | ("R", 180) => {
+            ('L', 180) | ('R', 180) => {
                 wpt_n = -wpt_n;
                 wpt_e = -wpt_e;
             }
-            _ => panic!("bad direction!"),
+            ('L', 0) | ('R', 0) => (),
+            _ => unreachable!("steps are checked by Step::from_str"),
         }
     });
 
@@ -69,13 +106,35 @@ fn navigate2(data: &str) -> usize {
 
 // main
 pub fn run() -> Result<(usize, usize)> {
-    let data = utils::read_file("data/input-12.txt")?;
     let answers = utils::read_i64s("data/output-12.txt")?;
 
-    let p1 = navigate(&data);
-    let p2 = navigate2(&data);
+    let p1 = utils::try_stream(utils::stream_parsed("data/input-12.txt")?, |steps| {
+        Ok(navigate(steps))
+    })?;
+    let p2 = utils::try_stream(utils::stream_parsed("data/input-12.txt")?, |steps| {
+        Ok(navigate2(steps))
+    })?;
 
     assert_eq!(p1, answers[0] as usize);
     assert_eq!(p2, answers[1] as usize);
     Ok((p1, p2))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_checked_when_parsed() {
+        assert!("X10".parse::<Step>().is_err());
+        assert!("R45".parse::<Step>().is_err());
+        assert!("F".parse::<Step>().is_err());
+        assert_eq!("L-90".parse::<Step>().unwrap().value, 270);
+        assert_eq!("R360".parse::<Step>().unwrap().value, 0);
+
+        let steps = ["F10", "N3", "R360", "F7", "L450", "F11"];
+        let parse = || steps.iter().map(|s| s.parse::<Step>().unwrap());
+        assert_eq!(navigate(parse()), 31);
+        assert_eq!(navigate2(parse()), 274);
+    }
+}
@@ -1,6 +1,6 @@
 // ==== day 13
 
-use crate::utils;
+use crate::utils::{self, ParseError};
 use anyhow::Result;
 
 #[derive(Debug)]
@@ -9,19 +9,17 @@ struct BusRoute {
     time: usize,
 }
 
-fn parse_bus_routes(input: &str) -> Vec<BusRoute> {
-    input
-        .trim()
-        .split(',')
+fn parse_bus_routes(input: &str) -> Result<Vec<BusRoute>, utils::ParseError> {
+    // "x" marks an out of service bus, which still occupies an index
+    let slots: Vec<Option<usize>> = utils::parse_separated_with(input, ',', |s| match s {
+        "x" => Ok(None),
+        _ => s.parse::<usize>().map(Some),
+    })?;
+    Ok(slots
+        .iter()
         .enumerate()
-        .filter_map(|(i, s)| {
-            if let Ok(t) = s.parse::<usize>() {
-                Some(BusRoute { index: i, time: t })
-            } else {
-                None
-            }
-        })
-        .collect()
+        .filter_map(|(i, t)| t.map(|time| BusRoute { index: i, time }))
+        .collect())
 }
 
 fn mins_to_next_departure(dep_time: usize, bus: &BusRoute) -> usize {
@@ -84,9 +82,13 @@ pub fn run() -> Result<(usize, i64)> {
 
     // parse initial start time and bus routes
     let parsed: Vec<&str> = data.splitn(2, '\n').collect();
-    assert_eq!(parsed.len(), 2);
-    let departure_time = parsed[0].parse::<usize>().unwrap();
-    let routes: Vec<BusRoute> = parse_bus_routes(parsed[1]);
+    if parsed.len() != 2 {
+        return Err(ParseError::new(2, "expected the bus routes after the departure time").into());
+    }
+    let departure_time: usize = parsed[0]
+        .parse()
+        .map_err(|e| ParseError::new(1, format!("bad departure time '{}': {}", parsed[0], e)))?;
+    let routes: Vec<BusRoute> = parse_bus_routes(parsed[1]).map_err(|e| e.offset(1))?;
 
     // find which of the bus routes departs most soon after departure_time
     let mut next_departures: Vec<(usize, usize)> = routes
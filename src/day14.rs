@@ -1,7 +1,7 @@
 // ==== day 14
 
 use crate::utils;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 
 #[derive(Copy, Clone, Debug)]
@@ -17,46 +17,45 @@ enum Instruction {
     },
 }
 
-fn parse_mask(s: &str) -> Instruction {
-    let maskstr = &s[s.find(" = ").unwrap() + 3..];
+fn parse_mask(s: &str) -> Result<Instruction> {
+    let maskstr = &s[s.find(" = ").ok_or_else(|| anyhow!("missing ' = '"))? + 3..];
     let ones_str = maskstr.replace('X', "0");
     let zeroes_str = maskstr
         .replace('1', "X")
         .replace('0', "1")
         .replace('X', "0");
     let exes_str = maskstr.replace('1', "0").replace('X', "1");
-    Instruction::Mask {
-        exes_mask: u64::from_str_radix(&exes_str, 2).unwrap(),
-        ones_mask: u64::from_str_radix(&ones_str, 2).unwrap(),
-        zeroes_mask: u64::from_str_radix(&zeroes_str, 2).unwrap(),
-    }
+    Ok(Instruction::Mask {
+        exes_mask: u64::from_str_radix(&exes_str, 2)?,
+        ones_mask: u64::from_str_radix(&ones_str, 2)?,
+        zeroes_mask: u64::from_str_radix(&zeroes_str, 2)?,
+    })
 }
 
-fn parse_write(s: &str) -> Instruction {
-    Instruction::Write {
-        address: s[4..s.find(']').unwrap()].parse().unwrap(),
-        value: s[s.find(" = ").unwrap() + 3..].parse().unwrap(),
-    }
+fn parse_write(s: &str) -> Result<Instruction> {
+    let close = s.find(']').ok_or_else(|| anyhow!("missing ']'"))?;
+    let equals = s.find(" = ").ok_or_else(|| anyhow!("missing ' = '"))?;
+    Ok(Instruction::Write {
+        address: s[4..close].parse()?,
+        value: s[equals + 3..].parse()?,
+    })
 }
 
-fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(str::trim)
-        .map(|l| match &l[0..4] {
-            "mask" => parse_mask(l),
-            "mem[" => parse_write(l),
-            _ => unreachable!("bad input"),
-        })
-        .collect()
+fn parse_instruction(l: &str) -> Result<Instruction> {
+    let l = l.trim();
+    match l.get(0..4) {
+        Some("mask") => parse_mask(l),
+        Some("mem[") => parse_write(l),
+        _ => Err(anyhow!("unknown instruction '{}'", l)),
+    }
 }
 
-fn simulate1(program: &[Instruction]) -> u64 {
+fn simulate1(program: impl Iterator<Item = Instruction>) -> u64 {
     let mut memory: HashMap<u64, u64> = HashMap::new();
 
     let (mut cur_ones, mut cur_zeroes) = (0, 0);
-    program.iter().for_each(|i| {
-        match i {
+    program.for_each(|i| {
+        match &i {
             Instruction::Mask {
                 ones_mask,
                 zeroes_mask,
@@ -74,33 +73,12 @@ fn simulate1(program: &[Instruction]) -> u64 {
     memory.values().sum()
 }
 
-fn set_bit(n: u64, bit: usize, value: bool) -> u64 {
-    if value {
-        n | (1 << bit)
-    } else {
-        n & !(1 << bit)
-    }
-}
-
-fn address_masks_from_float_mask(float_mask: u64) -> impl Iterator<Item = u64> {
-    let possibilities = 2_u64.pow(float_mask.count_ones());
-    let one_positions: Vec<usize> = (0..36).filter(|i| float_mask & (1 << i) != 0).collect();
-    (0..possibilities).map(move |i| {
-        one_positions
-            .iter()
-            .enumerate()
-            .fold(0, |result, (j, pos)| {
-                set_bit(result, *pos, (i & (1 << j)) != 0)
-            })
-    })
-}
-
-fn simulate2(program: &[Instruction]) -> u64 {
+fn simulate2(program: impl Iterator<Item = Instruction>) -> u64 {
     let mut memory: HashMap<u64, u64> = HashMap::new();
 
     let (mut cur_exes, mut cur_ones, mut cur_zeroes) = (0, 0, 0);
-    program.iter().for_each(|i| {
-        match i {
+    program.for_each(|i| {
+        match &i {
             Instruction::Mask {
                 exes_mask,
                 ones_mask,
@@ -112,7 +90,7 @@ fn simulate2(program: &[Instruction]) -> u64 {
             }
             Instruction::Write { address, value } => {
                 let base_address = (*address | cur_ones) & !cur_exes;
-                address_masks_from_float_mask(cur_exes)
+                utils::gray_subsets(cur_exes)
                     .map(|a| a | base_address)
                     .for_each(|a| {
                         memory.insert(a, *value);
@@ -126,13 +104,11 @@ fn simulate2(program: &[Instruction]) -> u64 {
 
 // main
 pub fn run() -> Result<(u64, u64)> {
-    let data = utils::read_file("data/input-14.txt")?;
     let answers = utils::read_i64s("data/output-14.txt")?;
 
-    let parsed = parse(&data);
-
-    let p1 = simulate1(&parsed);
-    let p2 = simulate2(&parsed);
+    let stream = || utils::stream_parsed_with("data/input-14.txt", parse_instruction);
+    let p1 = utils::try_stream(stream()?, |program| Ok(simulate1(program)))?;
+    let p2 = utils::try_stream(stream()?, |program| Ok(simulate2(program)))?;
 
     assert_eq!(p1, answers[0] as u64);
     assert_eq!(p2, answers[1] as u64);
@@ -14,7 +14,7 @@ fn play_speaking_game(input: &[i64], n: usize) -> i64 {
 
     (seen.len()..n).fold(*input.last().unwrap(), |_, turn| {
         let num = match last_insert {
-            Some(prev_turn) => (turn - 1 - prev_turn),
+            Some(prev_turn) => turn - 1 - prev_turn,
             None => 0,
         } as i64;
 
@@ -25,11 +25,7 @@ fn play_speaking_game(input: &[i64], n: usize) -> i64 {
 
 // main
 pub fn run() -> Result<(i64, i64)> {
-    let data: Vec<i64> = utils::read_file("data/input-15.txt")?
-        .trim()
-        .split(',')
-        .map(|s| s.parse::<i64>().expect("parse error"))
-        .collect();
+    let data: Vec<i64> = utils::parse_separated(&utils::read_file("data/input-15.txt")?, ',')?;
     let answers = utils::read_i64s("data/output-15.txt")?;
 
     let p1 = play_speaking_game(&data, 2020);
@@ -1,8 +1,8 @@
 // ==== day 16
 
-use crate::utils;
-use anyhow::Result;
-use regex::Regex;
+use crate::utils::{self, FromCaptures};
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
 use std::collections::HashMap;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -17,44 +17,43 @@ impl Field {
     }
 }
 
-fn parse_fields(input: &str) -> Result<Vec<Field>> {
-    let re = Regex::new(
-        r"(?P<field>[\w\s]+): (?P<min1>\d+)\-(?P<max1>\d+) or (?P<min2>\d+)\-(?P<max2>\d+)",
-    )?;
-
-    Ok(re
-        .captures_iter(input)
-        .map(|caps| Field {
+impl FromCaptures for Field {
+    fn from_captures(caps: &Captures) -> Result<Self> {
+        Ok(Field {
             name: caps["field"].trim().to_owned(),
             ranges: vec![
                 std::ops::Range {
-                    start: caps["min1"].trim().parse::<i64>().unwrap(),
-                    end: caps["max1"].trim().parse::<i64>().unwrap() + 1_i64,
+                    start: utils::capture(caps, "min1")?,
+                    end: utils::capture::<i64>(caps, "max1")? + 1_i64,
                 },
                 std::ops::Range {
-                    start: caps["min2"].trim().parse::<i64>().unwrap(),
-                    end: caps["max2"].trim().parse::<i64>().unwrap() + 1_i64,
+                    start: utils::capture(caps, "min2")?,
+                    end: utils::capture::<i64>(caps, "max2")? + 1_i64,
                 },
             ],
         })
-        .collect())
+    }
 }
 
-fn parse_tickets(input: &str) -> Result<(Vec<i64>, Vec<Vec<i64>>)> {
-    let mut lines = input.lines().map(str::trim).filter(|l| l.contains(','));
-
-    let my_ticket: Vec<i64> = lines
-        .next()
-        .unwrap()
-        .split(',')
-        .map(|n| n.parse::<i64>().unwrap())
-        .collect();
+fn parse_fields(input: &utils::Record) -> Result<Vec<Field>> {
+    let re = Regex::new(
+        r"^(?P<field>[\w\s]+): (?P<min1>\d+)\-(?P<max1>\d+) or (?P<min2>\d+)\-(?P<max2>\d+)$",
+    )?;
 
-    let other_tickets: Vec<Vec<i64>> = lines
-        .map(|l| l.split(',').map(|n| n.parse::<i64>().unwrap()).collect())
-        .collect();
+    Ok(utils::parse_captures(&re, input.text).map_err(|e| e.offset(input.first_line - 1))?)
+}
 
-    Ok((my_ticket, other_tickets))
+// parse a "your ticket:" or "nearby tickets:" section, skipping the header line
+fn parse_tickets(input: &utils::Record) -> Result<Vec<Vec<i64>>> {
+    Ok(input
+        .text
+        .lines()
+        .enumerate()
+        .skip(1)
+        .map(|(i, l)| {
+            utils::parse_separated(l, ',').map_err(|e| e.offset(input.first_line + i - 1))
+        })
+        .collect::<Result<_, _>>()?)
 }
 
 fn ticket_error_rate(fields: &[Field], ticket: &[i64]) -> i64 {
@@ -74,7 +73,7 @@ fn find_field_locations(fields: &[Field], my_ticket: &[i64], tickets: &[Vec<i64>
     // find valid tickets
     let mut valid_tickets: Vec<&Vec<i64>> = tickets
         .iter()
-        .filter(|t| ticket_error_rate(&fields, t) == 0)
+        .filter(|t| ticket_error_rate(fields, t) == 0)
         .collect();
     let my_ticket = my_ticket.to_vec();
     valid_tickets.push(&my_ticket);
@@ -127,8 +126,15 @@ pub fn run() -> Result<(i64, i64)> {
     let data = utils::read_file("data/input-16.txt")?;
     let answers = utils::read_i64s("data/output-16.txt")?;
 
-    let fields = parse_fields(&data)?;
-    let (my_ticket, other_tickets) = parse_tickets(&data)?;
+    let sections = utils::records(&data);
+    if sections.len() != 3 {
+        return Err(anyhow!("expected 3 sections, found {}", sections.len()));
+    }
+    let fields = parse_fields(&sections[0])?;
+    let my_ticket = parse_tickets(&sections[1])?
+        .pop()
+        .ok_or_else(|| anyhow!("missing my ticket"))?;
+    let other_tickets = parse_tickets(&sections[2])?;
 
     // find invalid tickets, sum error rates
     let p1 = other_tickets
@@ -1,7 +1,7 @@
 // ==== day 18
 
 use crate::utils;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 
 peg::parser! {
     grammar arithmetic_parser() for str {
@@ -31,15 +31,22 @@ peg::parser! {
 
 // main
 pub fn run() -> Result<(i64, i64)> {
-    let data = utils::read_file("data/input-18.txt")?;
     let answers = utils::read_i64s("data/output-18.txt")?;
 
-    let p1 = data.lines().fold(0, |sum, line| {
-        sum + arithmetic_parser::arith_equal_precedence(line).unwrap()
-    });
-    let p2 = data.lines().fold(0, |sum, line| {
-        sum + arithmetic_parser::arith_addition_precedence(line).unwrap()
-    });
+    let p1 = utils::try_stream(
+        utils::stream_parsed_with(
+            "data/input-18.txt",
+            arithmetic_parser::arith_equal_precedence,
+        )?,
+        |results| Ok(results.sum()),
+    )?;
+    let p2 = utils::try_stream(
+        utils::stream_parsed_with(
+            "data/input-18.txt",
+            arithmetic_parser::arith_addition_precedence,
+        )?,
+        |results| Ok(results.sum()),
+    )?;
 
     assert_eq!(p1, answers[0]);
     assert_eq!(p2, answers[1]);
@@ -1,4 +1,5 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::env;
 
 mod day01;
 mod day02;
@@ -21,7 +22,7 @@ mod utils;
 
 // ==== main()
 
-fn main() -> Result<()> {
+fn run_all() -> Result<()> {
     println!("Day 01: {:?}", day01::run()?);
     println!("Day 02: {:?}", day02::run()?);
     println!("Day 03: {:?}", day03::run()?);
@@ -41,3 +42,17 @@ fn main() -> Result<()> {
     println!("Day 18: {:?}", day18::run()?);
     Ok(())
 }
+
+// with no arguments, solve every day; otherwise run a day's tools
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => run_all(),
+        Some("day02") => day02::cli(&args[1..]),
+        Some("day03") => day03::cli(&args[1..]),
+        Some("day04") => day04::cli(&args[1..]),
+        Some("day07") => day07::cli(&args[1..]),
+        Some("day08") => day08::cli(&args[1..]),
+        Some(cmd) => Err(anyhow!("unknown command '{}'", cmd)),
+    }
+}
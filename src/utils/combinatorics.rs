@@ -0,0 +1,386 @@
+// ==== combinatorics
+//
+// Not every routine in here is used by a puzzle solution (those which aren't
+// are marked dead_code); this is a small toolkit of generators and counting
+// functions to reach for when needed.
+
+// Lexicographic combinations.
+//
+// This algorithm generates all t-combinations of n numbers {0, 1, ..., n-1},
+// given n >= t >= 0. Additional variables c[t] and c[t+1] are used as
+// sentinels.
+//
+// Combinations are visited in lexicographic order of the reversed tuples
+// c[t-1]...c[1]c[0] (i.e. colexicographic order of c), which is the order
+// that rank_combination() and unrank_combination() below agree with.
+//
+// Source: Donald E. Knuth, The Art of Computer Programming, VOLUME 4A,
+//         Combinatorial Algorithms, Part 1, Section 7.2.1.3, Algorithm L,
+//         Page 358, First printing, January 2011.
+pub struct CombinationsState {
+    t: usize,
+    c: Vec<usize>,
+    done: bool,
+}
+
+pub fn combinations(t: usize, n: usize) -> CombinationsState {
+    let mut c = CombinationsState {
+        t,
+        c: vec![0; t + 2],
+        done: false,
+    };
+
+    // L1. Initialize
+    for i in 0..t {
+        c.c[i] = i;
+    }
+    c.c[t] = n;
+    c.c[t + 1] = 0;
+    c
+}
+
+impl Iterator for CombinationsState {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        // L2. Visit.
+        let ret = if self.done {
+            None
+        } else {
+            Some(self.c[0..self.c.len() - 2].to_vec())
+        };
+
+        // L3. Find j.
+        let mut j = 0;
+        while (self.c[j] + 1) == self.c[j + 1] {
+            self.c[j] = j;
+            j += 1;
+        }
+
+        // L4. Done?
+        if j >= self.t {
+            self.done = true;
+        };
+
+        // L5. Increase c[j].
+        self.c[j] += 1;
+
+        // return Some(visited) or None from L2 above
+        ret
+    }
+}
+
+// Number of ways to choose k items from n, or None if the result does not
+// fit in a u64.
+//
+// Uses the multiplicative formula C(n, i+1) = C(n, i) * (n - i) / (i + 1)
+// with k reduced to min(k, n - k), so every intermediate C(n, i) is no larger
+// than the final result and the product fits comfortably in a u128.
+#[allow(dead_code)]
+pub fn binomial(n: u64, k: u64) -> Option<u64> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        result = result * u128::from(n - i) / u128::from(i + 1);
+        if result > u128::from(u64::MAX) {
+            return None;
+        }
+    }
+    Some(result as u64)
+}
+
+// Rank of a t-combination in the order visited by combinations() above.
+//
+// The combination must be strictly increasing. By the combinatorial number
+// system, rank(c) = C(c[0], 1) + C(c[1], 2) + ... + C(c[t-1], t). Returns None
+// if the rank does not fit in a u64.
+#[allow(dead_code)]
+pub fn rank_combination(c: &[usize]) -> Option<u64> {
+    debug_assert!(c.windows(2).all(|w| w[0] < w[1]));
+    c.iter().enumerate().try_fold(0_u64, |rank, (i, x)| {
+        rank.checked_add(binomial(*x as u64, i as u64 + 1)?)
+    })
+}
+
+// The t-combination of {0, 1, ..., n-1} with the given rank, in the order
+// visited by combinations() above. Returns None if rank >= C(n, t).
+#[allow(dead_code)]
+pub fn unrank_combination(mut rank: u64, t: usize, n: usize) -> Option<Vec<usize>> {
+    // a C(n, t) too big for a u64 is bigger than any rank
+    if binomial(n as u64, t as u64).is_some_and(|total| rank >= total) {
+        return None;
+    }
+
+    // greedily choose the largest element first: c[j-1] is the largest x
+    // with C(x, j) <= rank
+    let mut c = vec![0; t];
+    let mut x = n;
+    for j in (1..=t).rev() {
+        loop {
+            x -= 1;
+            // likewise, a C(x, j) which overflows is bigger than rank
+            if let Some(b) = binomial(x as u64, j as u64).filter(|b| *b <= rank) {
+                rank -= b;
+                break;
+            }
+        }
+        c[j - 1] = x;
+    }
+    Some(c)
+}
+
+// Revolving-door combinations.
+//
+// This algorithm generates all t-combinations of n numbers {0, 1, ..., n-1}
+// in an order where each combination differs from the previous one by
+// removing a single element and adding another (a Gray code for
+// combinations). An additional variable c[t] = n is used as a sentinel.
+//
+// Knuth states the algorithm for n > t > 1. The degenerate cases t = 0 and
+// t = n are handled up front; t = 1 needs nothing special, as only step R3
+// applies to it.
+//
+// Source: Donald E. Knuth, The Art of Computer Programming, VOLUME 4A,
+//         Combinatorial Algorithms, Part 1, Section 7.2.1.3, Algorithm R,
+//         Page 363, First printing, January 2011.
+#[allow(dead_code)]
+pub struct RevolvingDoorState {
+    t: usize,
+    c: Vec<usize>,
+    done: bool,
+}
+
+#[allow(dead_code)]
+pub fn revolving_door(t: usize, n: usize) -> RevolvingDoorState {
+    // R1. Initialize.
+    let mut c: Vec<usize> = (0..t).collect();
+    c.push(n);
+    RevolvingDoorState { t, c, done: t > n }
+}
+
+#[allow(dead_code)]
+impl RevolvingDoorState {
+    // advance c to the next combination, returning false when exhausted
+    fn advance(&mut self) -> bool {
+        let t = self.t;
+        let n = self.c[t];
+        if t == 0 || t == n {
+            return false;
+        }
+
+        // the algorithm below uses Knuth's 1-based c_1..c_t, which are stored
+        // here as c[0]..c[t-1]
+        let c = &mut self.c;
+
+        // R3. Easy case?
+        let mut j = 2;
+        let mut try_decrease = if t % 2 == 1 {
+            if c[0] + 1 < c[1] {
+                c[0] += 1;
+                return true;
+            }
+            true
+        } else {
+            if c[0] > 0 {
+                c[0] -= 1;
+                return true;
+            }
+            false
+        };
+
+        while j <= t {
+            if try_decrease {
+                // R4. Try to decrease c_j.
+                if c[j - 1] >= j {
+                    c[j - 1] = c[j - 2];
+                    c[j - 2] = j - 2;
+                    return true;
+                }
+                j += 1;
+                try_decrease = false;
+            } else {
+                // R5. Try to increase c_j.
+                if c[j - 1] + 1 < c[j] {
+                    c[j - 2] = c[j - 1];
+                    c[j - 1] += 1;
+                    return true;
+                }
+                j += 1;
+                try_decrease = true;
+            }
+        }
+
+        // R6. Terminate.
+        false
+    }
+}
+
+impl Iterator for RevolvingDoorState {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done {
+            return None;
+        }
+
+        // R2. Visit.
+        let ret = self.c[0..self.t].to_vec();
+        self.done = !self.advance();
+        Some(ret)
+    }
+}
+
+// Binary reflected Gray code.
+//
+// Generates all 2^n n-bit codes, each differing from the previous one in
+// exactly one bit. The k-th code is k ^ (k >> 1), and the bit that changes
+// between codes k-1 and k is the lowest set bit of k.
+//
+// Source: Donald E. Knuth, The Art of Computer Programming, VOLUME 4A,
+//         Combinatorial Algorithms, Part 1, Section 7.2.1.1, Algorithm G,
+//         Page 286, First printing, January 2011.
+#[allow(dead_code)]
+pub struct GrayCodeState {
+    k: u64,
+    end: u64,
+}
+
+#[allow(dead_code)]
+pub fn gray_codes(n: u32) -> GrayCodeState {
+    assert!(n < 64, "gray code limited to 63 bits");
+    GrayCodeState { k: 0, end: 1 << n }
+}
+
+impl Iterator for GrayCodeState {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.k >= self.end {
+            return None;
+        }
+        let ret = self.k ^ (self.k >> 1);
+        self.k += 1;
+        Some(ret)
+    }
+}
+
+// All subsets of the bits set in mask, in Gray code order.
+//
+// Starts at 0 and flips one bit of mask per step, so each yielded value
+// differs from the previous one by a single bit.
+pub struct GraySubsetsState {
+    positions: Vec<u32>,
+    k: u64,
+    end: u64,
+    current: u64,
+}
+
+pub fn gray_subsets(mask: u64) -> GraySubsetsState {
+    let positions: Vec<u32> = (0..64).filter(|i| mask & (1 << i) != 0).collect();
+    assert!(positions.len() < 64, "gray subsets limited to 63 bits");
+    GraySubsetsState {
+        end: 1 << positions.len(),
+        positions,
+        k: 0,
+        current: 0,
+    }
+}
+
+impl Iterator for GraySubsetsState {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.k >= self.end {
+            return None;
+        }
+        if self.k > 0 {
+            // flip the bit corresponding to the lowest set bit of k
+            self.current ^= 1 << self.positions[self.k.trailing_zeros() as usize];
+        }
+        self.k += 1;
+        Some(self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn rank_and_unrank_follow_combinations_order() {
+        for n in 0..=10 {
+            for t in 0..=n {
+                let mut count = 0;
+                for (rank, c) in combinations(t, n).enumerate() {
+                    assert_eq!(rank_combination(&c), Some(rank as u64));
+                    assert_eq!(unrank_combination(rank as u64, t, n), Some(c));
+                    count += 1;
+                }
+                let total = binomial(n as u64, t as u64).unwrap();
+                assert_eq!(count, total);
+                assert_eq!(unrank_combination(total, t, n), None);
+            }
+        }
+    }
+
+    #[test]
+    fn unrank_past_u64_binomials() {
+        // C(100, 50) doesn't fit in a u64, but small ranks still exist
+        assert_eq!(binomial(100, 50), None);
+        assert_eq!(unrank_combination(0, 50, 100), Some((0..50).collect()));
+        let last: Vec<usize> = (50..100).collect();
+        assert_eq!(rank_combination(&last), None);
+        let c = unrank_combination(u64::MAX, 50, 100).unwrap();
+        assert_eq!(rank_combination(&c), Some(u64::MAX));
+    }
+
+    #[test]
+    fn revolving_door_matches_combinations() {
+        for n in 0..=10 {
+            for t in 0..=n {
+                let door: Vec<Vec<usize>> = revolving_door(t, n).collect();
+                let expected: BTreeSet<Vec<usize>> = combinations(t, n).collect();
+                assert_eq!(door.len(), expected.len());
+                assert_eq!(door.iter().cloned().collect::<BTreeSet<_>>(), expected);
+                for w in door.windows(2) {
+                    let a: BTreeSet<usize> = w[0].iter().copied().collect();
+                    let b: BTreeSet<usize> = w[1].iter().copied().collect();
+                    assert_eq!(a.difference(&b).count(), 1, "{:?} -> {:?}", w[0], w[1]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gray_subsets_are_one_bit_apart() {
+        for mask in [0, 1, 0b1011, 0b1010_0110, 0x3ff, 1 << 40 | 1 << 3] {
+            let subsets: Vec<u64> = gray_subsets(mask).collect();
+            assert_eq!(subsets.len(), 1 << mask.count_ones());
+            assert_eq!(subsets.iter().collect::<BTreeSet<_>>().len(), subsets.len());
+            assert!(subsets.iter().all(|s| s & !mask == 0));
+            assert!(subsets.windows(2).all(|w| (w[0] ^ w[1]).count_ones() == 1));
+        }
+    }
+
+    #[test]
+    fn gray_codes_are_one_bit_apart() {
+        for n in 0..=10 {
+            let codes: Vec<u64> = gray_codes(n).collect();
+            assert_eq!(codes.len(), 1 << n);
+            assert_eq!(codes.iter().collect::<BTreeSet<_>>().len(), codes.len());
+            assert!(codes.windows(2).all(|w| (w[0] ^ w[1]).count_ones() == 1));
+        }
+    }
+
+    #[test]
+    fn binomial_detects_overflow() {
+        assert_eq!(binomial(10, 3), Some(120));
+        assert_eq!(binomial(3, 10), Some(0));
+        assert_eq!(binomial(67, 33), Some(14_226_520_737_620_288_370));
+        assert_eq!(binomial(68, 34), None);
+    }
+}
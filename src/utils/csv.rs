@@ -0,0 +1,85 @@
+// ==== CSV
+//
+// Comma separated values as spreadsheets write them (RFC 4180), but one
+// record per line: quoted fields may contain commas and doubled quotes, not
+// line breaks.
+
+use super::ParseError;
+
+// one CSV line, quoting only the fields which need it; a missing field (None)
+// is an empty cell, and an empty field is quoted so the two can be told apart
+pub fn csv_line<S: AsRef<str>>(fields: &[Option<S>]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            let f = match f {
+                Some(f) => f.as_ref(),
+                None => return String::new(),
+            };
+            if f.is_empty() || f.contains([',', '"', '\n', '\r']) || f.trim() != f {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect();
+    fields.join(",")
+}
+
+// split one CSV line into its fields, each with the (1-based) column it starts
+// at; an empty cell is None, as opposed to a quoted empty field. Errors give the
+// column, and line 1, for the caller to offset.
+pub fn parse_csv_line(line: &str) -> Result<Vec<(usize, Option<String>)>, ParseError> {
+    let line = line.trim_end_matches('\r');
+    let mut fields = Vec::new();
+    let mut chars = line.chars().enumerate().peekable();
+    loop {
+        let mut field = String::new();
+        let column = chars.peek().map_or(line.chars().count(), |(i, _)| *i) + 1;
+        match chars.peek() {
+            Some((start, '"')) => {
+                let start = *start;
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) if matches!(chars.peek(), Some((_, '"'))) => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some((_, '"')) => break,
+                        Some((_, c)) => field.push(c),
+                        None => {
+                            return Err(ParseError::new(1, "unterminated quoted field")
+                                .with_column(start + 1))
+                        }
+                    }
+                }
+                match chars.next() {
+                    None => {
+                        fields.push((column, Some(field)));
+                        return Ok(fields);
+                    }
+                    Some((_, ',')) => fields.push((column, Some(field))),
+                    Some((i, _)) => {
+                        return Err(ParseError::new(1, "expected ',' after a quoted field")
+                            .with_column(i + 1))
+                    }
+                }
+            }
+            _ => {
+                let mut more = false;
+                for (_, c) in chars.by_ref() {
+                    if c == ',' {
+                        more = true;
+                        break;
+                    }
+                    field.push(c);
+                }
+                fields.push((column, Some(field).filter(|f| !f.is_empty())));
+                if !more {
+                    return Ok(fields);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// ==== Graphviz DOT output
+//
+// Just enough of the DOT language to write directed graphs with attributes,
+// so that days can export pictures without Graphviz being installed.
+
+use std::fmt;
+
+// (name, value) attribute pairs, written in order
+pub type Attrs = Vec<(&'static str, String)>;
+
+pub struct Digraph {
+    name: String,
+    graph: Attrs,                        // attributes of the whole graph
+    node_defaults: Attrs,                // attributes shared by every node
+    nodes: Vec<(String, Attrs)>,         // node id, attributes
+    edges: Vec<(String, String, Attrs)>, // from, to, attributes
+}
+
+// quote a DOT ID, escaping quotes and backslashes; "\n" in a label becomes a
+// line break in the picture
+fn quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn attr_list(attrs: &Attrs) -> String {
+    let attrs: Vec<String> = attrs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, quote(value)))
+        .collect();
+    attrs.join(", ")
+}
+
+impl Digraph {
+    pub fn new(name: &str) -> Self {
+        Digraph {
+            name: name.to_string(),
+            graph: Vec::new(),
+            node_defaults: Vec::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    pub fn graph_attr(&mut self, name: &'static str, value: &str) {
+        self.graph.push((name, value.to_string()));
+    }
+
+    pub fn node_default(&mut self, name: &'static str, value: &str) {
+        self.node_defaults.push((name, value.to_string()));
+    }
+
+    pub fn node(&mut self, id: &str, attrs: Attrs) {
+        self.nodes.push((id.to_string(), attrs));
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, attrs: Attrs) {
+        self.edges.push((from.to_string(), to.to_string(), attrs));
+    }
+}
+
+impl fmt::Display for Digraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "digraph {} {{", quote(&self.name))?;
+        for (name, value) in &self.graph {
+            writeln!(f, "    {}={};", name, quote(value))?;
+        }
+        if !self.node_defaults.is_empty() {
+            writeln!(f, "    node [{}];", attr_list(&self.node_defaults))?;
+        }
+        for (id, attrs) in &self.nodes {
+            writeln!(f, "    {} [{}];", quote(id), attr_list(attrs))?;
+        }
+        for (from, to, attrs) in &self.edges {
+            write!(f, "    {} -> {}", quote(from), quote(to))?;
+            if attrs.is_empty() {
+                writeln!(f, ";")?;
+            } else {
+                writeln!(f, " [{}];", attr_list(attrs))?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,312 @@
+// ==== 2D grid
+//
+// General purpose, so some of the API isn't needed by any day yet (and is
+// marked dead_code).
+
+use super::ParseError;
+use std::{
+    convert::TryFrom,
+    fmt,
+    ops::{Index, IndexMut},
+};
+
+// (row, col) position in a grid
+pub type Pos = (usize, usize);
+
+// (row, col) step to take from a position
+pub type Dir = (i64, i64);
+
+// up, right, down, left
+#[allow(dead_code)]
+pub const DIRECTIONS4: [Dir; 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+// the eight compass directions, clockwise starting from up
+pub const DIRECTIONS8: [Dir; 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+
+// which edges of the grid wrap around to the opposite side
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Wrap {
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+impl Wrap {
+    fn rows(self) -> bool {
+        self == Wrap::Vertical || self == Wrap::Both
+    }
+
+    fn cols(self) -> bool {
+        self == Wrap::Horizontal || self == Wrap::Both
+    }
+}
+
+// A rectangular grid of cells, stored row-major in a single Vec.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    wrap: Wrap,
+    cells: Vec<T>,
+}
+
+// map a signed coordinate onto 0..len, wrapping if asked to
+fn wrap_coord(x: i64, len: usize, wrap: bool) -> Option<usize> {
+    if len == 0 {
+        None
+    } else if wrap {
+        Some(x.rem_euclid(len as i64) as usize)
+    } else if 0 <= x && x < len as i64 {
+        Some(x as usize)
+    } else {
+        None
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    #[allow(dead_code)]
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Grid {
+            width,
+            height,
+            wrap: Wrap::None,
+            cells: vec![fill; width * height],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    // parse a char map (one row per line), converting each char with f
+    pub fn parse_with<E, F>(s: &str, mut f: F) -> Result<Self, ParseError>
+    where
+        E: fmt::Display,
+        F: FnMut(char) -> Result<T, E>,
+    {
+        let mut width = None;
+        let mut height = 0;
+        let mut cells = Vec::new();
+        for (i, l) in s.lines().enumerate() {
+            let row_start = cells.len();
+            for (j, c) in l.chars().enumerate() {
+                cells.push(f(c).map_err(|e| ParseError::new(i + 1, e).with_column(j + 1))?);
+            }
+            let row_width = cells.len() - row_start;
+            match width {
+                None => width = Some(row_width),
+                Some(w) if w != row_width => {
+                    return Err(ParseError::new(
+                        i + 1,
+                        format!("expected {} cells, found {}", w, row_width),
+                    ))
+                }
+                _ => (),
+            }
+            height += 1;
+        }
+        Ok(Grid {
+            width: width.unwrap_or(0),
+            height,
+            wrap: Wrap::None,
+            cells,
+        })
+    }
+
+    // parse a char map, converting each char with TryFrom<char>
+    pub fn parse(s: &str) -> Result<Self, ParseError>
+    where
+        T: TryFrom<char>,
+        T::Error: fmt::Display,
+    {
+        Self::parse_with(s, T::try_from)
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        if pos.0 < self.height && pos.1 < self.width {
+            self.cells.get(pos.0 * self.width + pos.1)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        if pos.0 < self.height && pos.1 < self.width {
+            self.cells.get_mut(pos.0 * self.width + pos.1)
+        } else {
+            None
+        }
+    }
+
+    // look up a signed position, applying wrap-around
+    pub fn get_signed(&self, row: i64, col: i64) -> Option<&T> {
+        let row = wrap_coord(row, self.height, self.wrap.rows())?;
+        let col = wrap_coord(col, self.width, self.wrap.cols())?;
+        self.get((row, col))
+    }
+
+    // the position one step from pos in direction dir, if it's on the grid
+    pub fn step(&self, pos: Pos, dir: Dir) -> Option<Pos> {
+        Some((
+            wrap_coord(pos.0 as i64 + dir.0, self.height, self.wrap.rows())?,
+            wrap_coord(pos.1 as i64 + dir.1, self.width, self.wrap.cols())?,
+        ))
+    }
+
+    // all positions, in row-major order
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        let width = self.width;
+        (0..self.height).flat_map(move |i| (0..width).map(move |j| (i, j)))
+    }
+
+    // all cells with their positions, in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    // the distinct positions one step from pos in each direction, other than
+    // pos itself; with wrapping on a grid 1 or 2 cells across, several
+    // directions can lead to the same cell, or back to pos
+    fn neighbours(&self, pos: Pos, dirs: &[Dir]) -> impl Iterator<Item = Pos> {
+        let mut ret: Vec<Pos> = Vec::with_capacity(dirs.len());
+        for p in dirs.iter().filter_map(|d| self.step(pos, *d)) {
+            if p != pos && !ret.contains(&p) {
+                ret.push(p);
+            }
+        }
+        ret.into_iter()
+    }
+
+    // the up to 4 orthogonally adjacent positions
+    #[allow(dead_code)]
+    pub fn neighbours4(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        self.neighbours(pos, &DIRECTIONS4)
+    }
+
+    // the up to 8 orthogonally and diagonally adjacent positions
+    pub fn neighbours8(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        self.neighbours(pos, &DIRECTIONS8)
+    }
+
+    // the positions visited stepping repeatedly from (but not including)
+    // start in direction dir, until leaving the grid or, when wrapping,
+    // arriving back at start
+    pub fn ray(&self, start: Pos, dir: Dir) -> impl Iterator<Item = Pos> + '_ {
+        let mut pos = if dir == (0, 0) {
+            None
+        } else {
+            self.step(start, dir)
+        };
+        std::iter::from_fn(move || {
+            let ret = pos.filter(|p| *p != start)?;
+            pos = self.step(ret, dir);
+            Some(ret)
+        })
+    }
+
+    // the first position along a ray from start whose cell matches pred
+    pub fn cast_ray<P>(&self, start: Pos, dir: Dir, mut pred: P) -> Option<Pos>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.ray(start, dir).find(|p| pred(&self[*p]))
+    }
+
+    // build a new grid of the same shape by applying f to every cell
+    pub fn map<U, F>(&self, mut f: F) -> Grid<U>
+    where
+        F: FnMut(Pos, &T) -> U,
+    {
+        Grid {
+            width: self.width,
+            height: self.height,
+            wrap: self.wrap,
+            cells: self.iter().map(|(p, t)| f(p, t)).collect(),
+        }
+    }
+
+    // number of cells matching pred
+    pub fn count<P>(&self, mut pred: P) -> usize
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.cells.iter().filter(|t| pred(t)).count()
+    }
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos).expect("grid index out of bounds")
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        self.get_mut(pos).expect("grid index out of bounds")
+    }
+}
+
+// render one row per line, each cell using its own Display
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)) {
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_neighbours_are_distinct_and_exclude_pos() {
+        let narrow = Grid::new(1, 3, 0).with_wrap(Wrap::Both);
+        assert_eq!(
+            narrow.neighbours4((1, 0)).collect::<Vec<_>>(),
+            [(0, 0), (2, 0)]
+        );
+        assert_eq!(
+            narrow.neighbours8((1, 0)).collect::<Vec<_>>(),
+            [(0, 0), (2, 0)]
+        );
+
+        let small = Grid::new(2, 2, 0).with_wrap(Wrap::Both);
+        assert_eq!(
+            small.neighbours8((0, 0)).collect::<Vec<_>>(),
+            [(1, 0), (1, 1), (0, 1)]
+        );
+
+        let unwrapped = Grid::new(3, 3, 0);
+        assert_eq!(unwrapped.neighbours8((1, 1)).count(), 8);
+        assert_eq!(unwrapped.neighbours4((0, 0)).count(), 2);
+    }
+}
@@ -0,0 +1,179 @@
+// ==== parsing helpers
+
+use anyhow::{anyhow, Context, Result};
+use regex::{Captures, Regex};
+use std::{error::Error, fmt, str::FromStr};
+
+// A parse failure, with the (1-based) line and, when known, column it
+// occurred at.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: impl fmt::Display) -> Self {
+        ParseError {
+            line,
+            column: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    // shift the line number, for errors in text which didn't start on line 1
+    // of its file (e.g. one record out of many)
+    pub fn offset(mut self, lines: usize) -> Self {
+        self.line += lines;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+// read a file and parse each of its lines into a T
+pub fn read_parsed<T>(filename: &str) -> Result<Vec<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_lines(&super::read_file(filename)?)
+        .with_context(|| format!("failed to parse {}", filename))
+}
+
+// parse each line with f
+pub fn parse_lines_with<T, E, F>(s: &str, mut f: F) -> Result<Vec<T>, ParseError>
+where
+    E: fmt::Display,
+    F: FnMut(&str) -> Result<T, E>,
+{
+    s.lines()
+        .enumerate()
+        .map(|(i, l)| f(l).map_err(|e| ParseError::new(i + 1, e)))
+        .collect()
+}
+
+// parse each line into a T
+pub fn parse_lines<T>(s: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_lines_with(s, str::parse)
+}
+
+// parse a single-line list like "7,13,x,x,59" with f, trimming each item
+pub fn parse_separated_with<T, E, F>(s: &str, sep: char, mut f: F) -> Result<Vec<T>, ParseError>
+where
+    E: fmt::Display,
+    F: FnMut(&str) -> Result<T, E>,
+{
+    let mut column = 1;
+    s.trim_end()
+        .split(sep)
+        .map(|item| {
+            let item_column = column + item.chars().take_while(|c| c.is_whitespace()).count();
+            column += item.chars().count() + 1;
+            f(item.trim()).map_err(|e| ParseError::new(1, e).with_column(item_column))
+        })
+        .collect()
+}
+
+// parse a single-line list like "0,3,6" into Ts
+pub fn parse_separated<T>(s: &str, sep: char) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_separated_with(s, sep, str::parse)
+}
+
+// A group of consecutive non-blank lines, as found in the blank-line
+// separated inputs (e.g. passports, customs answers). Line numbers are
+// 1-based and inclusive.
+#[derive(Debug)]
+pub struct Record<'a> {
+    pub first_line: usize,
+    pub last_line: usize,
+    pub text: &'a str,
+}
+
+// split text into records separated by one or more blank lines
+pub fn records(s: &str) -> Vec<Record<'_>> {
+    let mut ret = Vec::new();
+    let mut start: Option<(usize, usize)> = None; // (line, byte offset) of the first line
+    let mut end = (0, 0); // (line, byte offset) of the end of the last non-blank line
+    let mut offset = 0;
+
+    for (i, l) in s.split('\n').enumerate() {
+        if l.trim().is_empty() {
+            if let Some((first_line, first)) = start.take() {
+                ret.push(Record {
+                    first_line,
+                    last_line: end.0,
+                    text: &s[first..end.1],
+                });
+            }
+        } else {
+            start.get_or_insert((i + 1, offset));
+            end = (i + 1, offset + l.trim_end_matches('\r').len());
+        }
+        offset += l.len() + 1;
+    }
+    if let Some((first_line, first)) = start {
+        ret.push(Record {
+            first_line,
+            last_line: end.0,
+            text: &s[first..end.1],
+        });
+    }
+    ret
+}
+
+// types which can be built from the captures of a regex match
+pub trait FromCaptures: Sized {
+    fn from_captures(caps: &Captures) -> Result<Self>;
+}
+
+// parse a named capture group into a T
+pub fn capture<T>(caps: &Captures, name: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let s = caps
+        .name(name)
+        .ok_or_else(|| anyhow!("missing capture group '{}'", name))?
+        .as_str();
+    s.parse()
+        .map_err(|e| anyhow!("bad {} '{}': {}", name, s, e))
+}
+
+// match re against a single line, and build a T from the captures
+pub fn parse_capture<T: FromCaptures>(re: &Regex, l: &str) -> Result<T> {
+    match re.captures(l) {
+        Some(caps) => T::from_captures(&caps),
+        None => Err(anyhow!("expected a line matching /{}/, found '{}'", re, l)),
+    }
+}
+
+// match re against each line, and build a T from the captures; every line
+// must match
+pub fn parse_captures<T: FromCaptures>(re: &Regex, s: &str) -> Result<Vec<T>, ParseError> {
+    parse_lines_with(s, |l| parse_capture(re, l))
+}
@@ -0,0 +1,98 @@
+// ==== streaming input
+//
+// For inputs too large to hold in memory, these read a file one line at a
+// time through a BufReader instead of loading it into a String.
+
+use super::ParseError;
+use anyhow::{Context, Result};
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    rc::Rc,
+    str::FromStr,
+};
+
+// stream the lines of a file, each with its 1-based line number
+pub fn stream_lines(filename: &str) -> Result<impl Iterator<Item = Result<(usize, String)>>> {
+    let file = File::open(filename).with_context(|| format!("failed to open {}", filename))?;
+    let filename = filename.to_string();
+    Ok(BufReader::new(file).lines().enumerate().map(move |(i, l)| {
+        l.map(|l| (i + 1, l))
+            .with_context(|| format!("failed to read {}", filename))
+    }))
+}
+
+// stream the lines of a file, parsing each with f
+pub fn stream_parsed_with<T, E, F>(
+    filename: &str,
+    mut f: F,
+) -> Result<impl Iterator<Item = Result<T>>>
+where
+    E: fmt::Display,
+    F: FnMut(&str) -> Result<T, E>,
+{
+    let name = filename.to_string();
+    Ok(stream_lines(filename)?.map(move |l| {
+        let (i, l) = l?;
+        f(&l)
+            .map_err(|e| ParseError::new(i, e))
+            .with_context(|| format!("failed to parse {}", name))
+    }))
+}
+
+// stream the lines of a file, parsing each into a T
+pub fn stream_parsed<T>(filename: &str) -> Result<impl Iterator<Item = Result<T>>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    stream_parsed_with(filename, str::parse)
+}
+
+// An iterator adapter which yields the Ok values of a stream, stopping at
+// (and remembering) the first error.
+pub struct Shunt<I> {
+    iter: I,
+    error: Rc<RefCell<Option<anyhow::Error>>>,
+}
+
+impl<T, I> Iterator for Shunt<I>
+where
+    I: Iterator<Item = Result<T>>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error.borrow().is_some() {
+            return None;
+        }
+        match self.iter.next()? {
+            Ok(t) => Some(t),
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                None
+            }
+        }
+    }
+}
+
+// run a solver which takes a plain iterator of T over a fallible stream,
+// failing with the stream's first error if there is one
+pub fn try_stream<T, R, I, F>(iter: I, f: F) -> Result<R>
+where
+    I: Iterator<Item = Result<T>>,
+    F: FnOnce(Shunt<I>) -> Result<R>,
+{
+    let error = Rc::new(RefCell::new(None));
+    let ret = f(Shunt {
+        iter,
+        error: Rc::clone(&error),
+    });
+    let error = error.borrow_mut().take();
+    match error {
+        Some(e) => Err(e),
+        None => ret,
+    }
+}